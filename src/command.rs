@@ -1,11 +1,10 @@
-
 use std::process::Command;
 
 use log::*;
 
 use crate::error::{Result, BenchError};
 
-fn output_into_string(mut command: Command) -> Result<String> {
+pub fn output_into_string(mut command: Command) -> Result<String> {
     let cmd_str = format!("{:?}", command);
     trace!("executing: {}", cmd_str);
     let output = command.output()?;
@@ -26,66 +25,6 @@ fn output_into_string(mut command: Command) -> Result<String> {
     }
 }
 
-/// Returns: shown messages
-pub fn ssh(user_name: &str, ip: &str, remote_cmd: &str) -> Result<String> {
-    let mut command = Command::new("ssh");
-    command.arg(format!("{}@{}", user_name, ip)).arg(remote_cmd);
-
-    output_into_string(command).map_err(|e| e.as_remote_if_possible(ip))
-}
-
-pub fn scp_to(is_dir: bool, user_name: &str, ip: &str, local_path: &str, remote_path: &str) -> Result<String> {
-    let mut command = Command::new("scp");
-
-    if is_dir {
-        command.arg("-r");
-    }
-    
-    command.arg(local_path);
-    command.arg(format!("{}@{}:{}", user_name, ip, remote_path));
-
-    match output_into_string(command).map_err(|e| e.as_remote_if_possible(ip)) {
-        Err(BenchError::CommandFailedOnRemote(_, _, 2, _)) =>
-            Err(BenchError::FileNotFound(local_path.to_owned())),
-        other => other
-    }
-}
-
-pub fn scp_from(is_dir: bool, user_name: &str, ip: &str, remote_path: &str,
-        local_path: &str) -> Result<String> {
-    let mut command = Command::new("scp");
-
-    if is_dir {
-        command.arg("-r");
-    }
-    
-    command.arg(format!("{}@{}:{}", user_name, ip, remote_path));
-    command.arg(local_path);
-
-    match output_into_string(command).map_err(|e| e.as_remote_if_possible(ip)) {
-        Err(BenchError::CommandFailedOnRemote(_, _, 2, _)) =>
-            Err(BenchError::FileNotFound(remote_path.to_owned())),
-        other => other
-    }
-}
-
-pub fn cp(is_dir: bool, source: &str, dest: &str) -> Result<String> {
-    let mut command = Command::new("cp");
-
-    if is_dir {
-        command.arg("-r");
-    }
-    
-    command.arg(source);
-    command.arg(dest);
-
-    match output_into_string(command) {
-        Err(BenchError::CommandFailed(_, 2, _)) =>
-            Err(BenchError::FileNotFound(source.to_owned())),
-        other => other
-    }
-}
-
 pub fn ls(path: &str) -> Result<String> {
     let mut command = Command::new("ls");
     command.arg(path);
@@ -98,4 +37,12 @@ pub fn ls(path: &str) -> Result<String> {
             Err(BenchError::FileNotFound(path.to_owned())),
         other => other
     }
-}
\ No newline at end of file
+}
+
+// Compresses each file under the directory into a .gz file
+pub fn gzip_dir(path: &str) -> Result<String> {
+    let mut command = Command::new("gzip");
+    command.arg("-r");
+    command.arg(path);
+    output_into_string(command)
+}
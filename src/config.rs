@@ -1,17 +1,29 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
-use std::string::ToString;
 
 use serde::Deserialize;
 
 use crate::error::{Result, BenchError};
+use crate::connections::Role;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Config {
     pub system: System,
     pub jdk: Jdk,
-    pub machines: Machines
+    pub machines: Machines,
+    #[serde(default)]
+    pub local: Local,
+    #[serde(default)]
+    pub timeouts: Timeouts,
+    #[serde(default)]
+    pub patterns: Patterns,
+    #[serde(default)]
+    pub ports: Ports,
+    #[serde(default)]
+    pub pinning: Pinning
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -22,14 +34,13 @@ pub struct System {
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Jdk {
+    #[allow(dead_code)] // Reserved
     pub use_custom_jdk: bool,
     pub dir_name: String,
     pub package_path: String,
     pub vmargs: JdkVmargs,
     #[serde(skip)]
-    pub package_filename: String,
-    #[serde(skip)]
-    pub remote_java_bin: String
+    pub package_filename: String
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -40,24 +51,421 @@ pub struct JdkVmargs {
 }
 
 #[derive(Deserialize, Debug, Clone)]
+#[serde(try_from = "RawMachines")]
 pub struct Machines {
-    #[serde(skip)]
     pub all: Vec<String>,
     pub sequencer: Option<String>,
     pub servers: Vec<String>,
-    pub clients: Vec<String>
+    pub clients: Vec<String>,
+    // The settings of the machines declared as tables
+    pub hosts: HashMap<String, HostConfig>,
+    // The loopback address standing for each machine under `--local`
+    pub local_addresses: HashMap<String, String>
+}
+
+// The settings overriding the global ones for a machine. The paths
+// on the machine are relative to the home directory, except that
+// `java_bin` is relative to the working directory.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HostConfig {
+    pub user: Option<String>,
+    pub ssh_port: Option<u16>,
+    pub identity_file: Option<String>,
+    pub work_dir: Option<String>,
+    // A java installed on the machine, which is used instead of the JDK
+    // deployed by init-env
+    pub java_bin: Option<String>,
+    // Reached through this host (`[user@]host[:port]`) as ssh -J
+    pub jump_host: Option<String>
+}
+
+// A machine is either an ip or a table with the ip and its settings
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MachineEntry {
+    Ip(String),
+    Host(HostEntry)
+}
+
+// The fields are listed here instead of flattening `HostConfig`,
+// which would let misspelled settings pass silently
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HostEntry {
+    ip: String,
+    user: Option<String>,
+    ssh_port: Option<u16>,
+    identity_file: Option<String>,
+    work_dir: Option<String>,
+    java_bin: Option<String>,
+    jump_host: Option<String>
+}
+
+impl HostEntry {
+    fn into_config(self) -> (String, HostConfig) {
+        (self.ip, HostConfig {
+            user: self.user,
+            ssh_port: self.ssh_port,
+            identity_file: self.identity_file,
+            work_dir: self.work_dir,
+            java_bin: self.java_bin,
+            jump_host: self.jump_host
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct RawMachines {
+    sequencer: Option<MachineEntry>,
+    servers: Vec<MachineEntry>,
+    clients: Vec<MachineEntry>
+}
+
+impl TryFrom<RawMachines> for Machines {
+    type Error = String;
+
+    fn try_from(raw: RawMachines) -> std::result::Result<Machines, String> {
+        let mut hosts: HashMap<String, HostConfig> = HashMap::new();
+        let mut read = |entry: MachineEntry| -> std::result::Result<String, String> {
+            let (ip, config) = match entry {
+                MachineEntry::Ip(ip) => return Ok(ip),
+                MachineEntry::Host(host) => host.into_config()
+            };
+            match hosts.get(&ip) {
+                Some(existing) if *existing != config => Err(format!(
+                    "machine '{}' is declared with different settings", ip)),
+                _ => {
+                    hosts.insert(ip.clone(), config);
+                    Ok(ip)
+                }
+            }
+        };
+
+        if raw.servers.is_empty() || raw.clients.is_empty() {
+            return Err("there should be at least a server and a client".to_owned());
+        }
+
+        let sequencer = raw.sequencer.map(&mut read).transpose()?;
+        let servers = raw.servers.into_iter().map(&mut read)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let clients = raw.clients.into_iter().map(&mut read)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut machines = Machines {
+            all: Vec::new(),
+            sequencer,
+            servers,
+            clients,
+            hosts,
+            local_addresses: HashMap::new()
+        };
+        machines.generate_all_ips();
+        Ok(machines)
+    }
+}
+
+impl Machines {
+    fn generate_all_ips(&mut self) {
+        self.all.clear();
+        if let Some(seq) = &self.sequencer {
+            self.all.push(seq.clone());
+        }
+        self.all.append(&mut self.servers.clone());
+        self.all.append(&mut self.clients.clone());
+    }
+
+    // Replaces the machines with loopback addresses. A machine used
+    // by many roles is known by its first address.
+    pub fn use_local_addresses(&mut self) {
+        let sequencer = self.sequencer.as_ref().map(|_| "127.0.0.1".to_owned());
+        let servers: Vec<String> = (1 ..= self.servers.len())
+            .map(|id| format!("127.0.1.{}", id)).collect();
+        let clients: Vec<String> = (1 ..= self.clients.len())
+            .map(|id| format!("127.0.2.{}", id)).collect();
+
+        let pairs = self.sequencer.iter().zip(&sequencer)
+            .chain(self.servers.iter().zip(&servers))
+            .chain(self.clients.iter().zip(&clients));
+        for (ip, address) in pairs {
+            self.local_addresses.entry(ip.clone())
+                .or_insert_with(|| address.clone());
+        }
+
+        self.sequencer = sequencer;
+        self.servers = servers;
+        self.clients = clients;
+        self.generate_all_ips();
+        // The settings for reaching the real machines do not apply
+        self.hosts.clear();
+    }
+
+    // Output: the address of the machine, which is a loopback
+    // address under `--local`
+    pub fn address<'a>(&'a self, ip: &'a str) -> &'a str {
+        self.local_addresses.get(ip).map_or(ip, |address| address.as_str())
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Local {
+    #[serde(skip)]
+    pub enabled: bool,
+    // The directory holding a working directory for each local machine
+    pub root_dir: Option<String>,
+    // The java used by the local processes
+    pub java_bin: Option<String>
+}
+
+// All in seconds
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Timeouts {
+    // For a process to show up after starting it
+    pub startup: u64,
+    // For a server to be ready after starting it
+    pub ready: u64,
+    // Added to WARM_UP_INTERVAL + BENCHMARK_INTERVAL for the clients
+    // to finish benchmarking
+    pub run_slack: u64,
+    // For the clients to finish loading. No limit if it is not set.
+    pub loading: Option<u64>
+}
+
+impl Default for Timeouts {
+    fn default() -> Timeouts {
+        Timeouts {
+            startup: 60,
+            ready: 600,
+            run_slack: 600,
+            loading: None
+        }
+    }
+}
+
+// The ports of a role are taken from `[first, last]` on each machine
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(from = "(usize, usize)")]
+pub struct PortRange {
+    pub first: usize,
+    pub last: usize
+}
+
+impl From<(usize, usize)> for PortRange {
+    fn from((first, last): (usize, usize)) -> PortRange {
+        PortRange { first, last }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Ports {
+    pub sequencer: PortRange,
+    pub servers: PortRange,
+    pub clients: PortRange
+}
+
+impl Default for Ports {
+    fn default() -> Ports {
+        let range = PortRange { first: 30000, last: 30999 };
+        Ports {
+            sequencer: range,
+            servers: range,
+            clients: range
+        }
+    }
+}
+
+// Where a process runs and allocates memory. A field that is not set
+// takes the one of the role.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PinSettings {
+    // A cpu list for `taskset -c`, e.g. "0-7,16-23"
+    pub cpus: Option<String>,
+    // Runs the process on the cpus of the NUMA node with `numactl`
+    pub numa_node: Option<usize>,
+    // How the memory is allocated on the NUMA node (bind by default)
+    pub memory: Option<MemoryPolicy>
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryPolicy {
+    Bind,
+    Preferred,
+    Interleave
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct RolePinning {
+    #[serde(flatten)]
+    pub common: PinSettings,
+    // The settings of the processes with the ids (e.g. "0")
+    #[serde(default)]
+    pub processes: HashMap<String, PinSettings>
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Pinning {
+    #[serde(default)]
+    pub sequencer: PinSettings,
+    #[serde(default)]
+    pub servers: RolePinning,
+    #[serde(default)]
+    pub clients: RolePinning
+}
+
+// The regexes for reading the logs of the processes. A field that is
+// not set takes the one in the outer table.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PatternSet {
+    // A line matching any of them fails the job...
+    pub error: Option<Vec<String>>,
+    // ...unless it also matches any of them
+    pub allow: Option<Vec<String>>,
+    // A server (or the sequencer) is ready
+    pub ready: Option<String>,
+    // A client finished
+    pub finish: Option<String>
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ActionPatterns {
+    #[serde(flatten)]
+    pub common: PatternSet,
+    #[serde(default)]
+    pub loading: PatternSet,
+    #[serde(default)]
+    pub benchmarking: PatternSet
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Patterns {
+    #[serde(flatten)]
+    pub common: ActionPatterns,
+    #[serde(default)]
+    pub server: ActionPatterns,
+    #[serde(default)]
+    pub sequencer: ActionPatterns,
+    #[serde(default)]
+    pub client: ActionPatterns
+}
+
+impl Ports {
+    fn validate(&self) -> Result<()> {
+        let ranges = [("sequencer", &self.sequencer), ("servers", &self.servers),
+            ("clients", &self.clients)];
+        for (role, range) in ranges.iter() {
+            if range.first > range.last || range.last > u16::MAX as usize {
+                return Err(BenchError::Message(format!(
+                    "invalid port range of the {}: [{}, {}]", role,
+                    range.first, range.last)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PinSettings {
+    fn or(&self, other: &PinSettings) -> PinSettings {
+        PinSettings {
+            cpus: self.cpus.clone().or_else(|| other.cpus.clone()),
+            numa_node: self.numa_node.or(other.numa_node),
+            memory: self.memory.or(other.memory)
+        }
+    }
+
+    fn validate(&self, name: &str) -> Result<()> {
+        if let Some(cpus) = &self.cpus {
+            let is_list = !cpus.is_empty() && cpus.chars()
+                .all(|c| c.is_ascii_digit() || c == ',' || c == '-');
+            if !is_list {
+                return Err(BenchError::Message(format!(
+                    "invalid cpu list of the {}: '{}'", name, cpus)));
+            }
+        }
+        if self.memory.is_some() && self.numa_node.is_none() {
+            return Err(BenchError::Message(format!(
+                "the memory policy of the {} needs a numa_node", name)));
+        }
+        Ok(())
+    }
+
+    /// The program wrapping the java, if any
+    pub fn program(&self) -> Option<&'static str> {
+        if self.numa_node.is_some() {
+            Some("numactl")
+        } else if self.cpus.is_some() {
+            Some("taskset")
+        } else {
+            None
+        }
+    }
+
+    /// The command running the java with the settings, which is empty
+    /// or ends with a space. `numactl` takes the cpu list if it is used.
+    pub fn command_prefix(&self) -> String {
+        let node = match self.numa_node {
+            Some(node) => node,
+            None => return match &self.cpus {
+                Some(cpus) => format!("taskset -c {} ", cpus),
+                None => String::new()
+            }
+        };
+
+        let cpus = match &self.cpus {
+            Some(cpus) => format!("--physcpubind={}", cpus),
+            None => format!("--cpunodebind={}", node)
+        };
+        let memory = match self.memory.unwrap_or(MemoryPolicy::Bind) {
+            MemoryPolicy::Bind => format!("--membind={}", node),
+            MemoryPolicy::Preferred => format!("--preferred={}", node),
+            MemoryPolicy::Interleave => "--interleave=all".to_owned()
+        };
+        format!("numactl {} {} ", cpus, memory)
+    }
+}
+
+impl Pinning {
+    fn validate(&self) -> Result<()> {
+        self.sequencer.validate("sequencer")?;
+        let roles = [("server", &self.servers), ("client", &self.clients)];
+        for (role, pinning) in roles.iter() {
+            pinning.common.validate(&format!("{}s", role))?;
+            for (id, settings) in &pinning.processes {
+                if id.parse::<usize>().is_err() {
+                    return Err(BenchError::Message(format!(
+                        "invalid {} id in [pinning]: '{}'", role, id)));
+                }
+                settings.validate(&format!("{} {}", role, id))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The settings of the process
+    pub fn settings(&self, role: Role, id: usize) -> PinSettings {
+        let pinning = match role {
+            Role::Sequencer => return self.sequencer.clone(),
+            Role::Server => &self.servers,
+            Role::Client => &self.clients
+        };
+        match pinning.processes.get(&id.to_string()) {
+            Some(settings) => settings.or(&pinning.common),
+            None => pinning.common.clone()
+        }
+    }
 }
 
 impl Config {
     pub fn from_file(path: &str) -> Result<Config> {
         // Read the file
-        let mut config_file = File::open(&path)?;
+        let mut config_file = File::open(path)?;
         let mut config_str = String::new();
         config_file.read_to_string(&mut config_str)?;
         let mut config: Config = toml::from_str(&config_str)?;
-
-        // All ips
-        config.generate_all_ips();
+        crate::connections::validate_patterns(&config.patterns)?;
+        config.ports.validate()?;
+        config.pinning.validate()?;
 
         // Get JDK package name
         let path = PathBuf::from(&config.jdk.package_path);
@@ -71,21 +479,119 @@ impl Config {
             }
         }
 
-        // Set the path to java
-        let mut path = PathBuf::from(&config.system.remote_work_dir);
-        path.push(&config.jdk.dir_name);
-        path.push("bin");
-        path.push("java");
-        config.jdk.remote_java_bin = path.display().to_string();
-
         Ok(config)
     }
 
-    fn generate_all_ips(&mut self) {
-        if let Some(seq) = &self.machines.sequencer {
-            self.machines.all.push(seq.clone());
+    // Replaces the machines with loopback addresses so that all the
+    // processes run on localhost. Each address works as a machine
+    // with its own working directory under the local root directory.
+    pub fn use_local_mode(&mut self) {
+        self.local.enabled = true;
+        self.machines.use_local_addresses();
+    }
+
+    /// The working directory on the machine
+    pub fn work_dir(&self, ip: &str) -> &str {
+        self.machines.hosts.get(ip)
+            .and_then(|host| host.work_dir.as_deref())
+            .unwrap_or(&self.system.remote_work_dir)
+    }
+
+    /// The java running the processes on the machine, which is
+    /// relative to the working directory
+    pub fn java_bin(&self, ip: &str) -> String {
+        if let Some(java_bin) = self.machines.hosts.get(ip)
+                .and_then(|host| host.java_bin.as_ref()) {
+            return java_bin.clone();
+        }
+        if self.local.enabled {
+            return self.local.java_bin.clone()
+                .unwrap_or_else(|| "java".to_owned());
         }
-        self.machines.all.append(&mut self.machines.servers.clone());
-        self.machines.all.append(&mut self.machines.clients.clone());
+        format!("{}/bin/java", self.jdk.dir_name)
     }
-}
\ No newline at end of file
+
+    // Whether the machine runs the JDK deployed by init-env
+    pub fn uses_deployed_jdk(&self, ip: &str) -> bool {
+        let has_own_java = self.machines.hosts.get(ip)
+            .is_some_and(|host| host.java_bin.is_some());
+        !self.local.enabled && !has_own_java
+    }
+
+    pub fn local_root_dir(&self) -> PathBuf {
+        match &self.local.root_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => std::env::temp_dir().join("auto-bencher-local")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::config_with_machines as parse;
+
+    #[test]
+    fn test_host_configs() {
+        let config = parse(r#"
+            sequencer = "10.0.0.1"
+            servers = [
+                "10.0.0.1",
+                { ip = "10.0.0.2", user = "bench", ssh_port = 2222, work_dir = "/data/ab" }
+            ]
+            clients = [{ ip = "10.0.0.3", java_bin = "/usr/bin/java", jump_host = "gw" }]
+        "#).unwrap();
+
+        assert_eq!(config.machines.all, ["10.0.0.1", "10.0.0.1", "10.0.0.2", "10.0.0.3"]);
+        assert_eq!(config.machines.hosts["10.0.0.2"].ssh_port, Some(2222));
+        assert_eq!(config.work_dir("10.0.0.1"), "work");
+        assert_eq!(config.work_dir("10.0.0.2"), "/data/ab");
+        assert_eq!(config.java_bin("10.0.0.2"), "jdk/bin/java");
+        assert_eq!(config.java_bin("10.0.0.3"), "/usr/bin/java");
+        assert!(config.uses_deployed_jdk("10.0.0.2"));
+        assert!(!config.uses_deployed_jdk("10.0.0.3"));
+
+        // The same machine with different settings
+        assert!(parse(r#"
+            servers = [{ ip = "10.0.0.2", user = "a" }]
+            clients = [{ ip = "10.0.0.2", user = "b" }]
+        "#).is_err());
+
+        assert!(parse(r#"
+            servers = ["10.0.0.1"]
+            clients = []
+        "#).is_err());
+
+        // A misspelled setting
+        assert!(parse(r#"
+            servers = [{ ip = "10.0.0.2", ssh_prot = 2222 }]
+            clients = ["10.0.0.3"]
+        "#).is_err());
+    }
+
+    #[test]
+    fn test_pinning() {
+        let pinning: Pinning = toml::from_str(r#"
+            [servers]
+            numa_node = 0
+            processes = { 1 = { numa_node = 1, memory = "interleave" }, 2 = { cpus = "8-15" } }
+
+            [clients]
+            cpus = "16-23"
+        "#).unwrap();
+        pinning.validate().unwrap();
+
+        let prefix = |role, id| pinning.settings(role, id).command_prefix();
+        assert_eq!(prefix(Role::Server, 0), "numactl --cpunodebind=0 --membind=0 ");
+        assert_eq!(prefix(Role::Server, 1), "numactl --cpunodebind=1 --interleave=all ");
+        assert_eq!(prefix(Role::Server, 2), "numactl --physcpubind=8-15 --membind=0 ");
+        assert_eq!(prefix(Role::Client, 0), "taskset -c 16-23 ");
+        assert_eq!(prefix(Role::Sequencer, 0), "");
+
+        let invalid: Pinning = toml::from_str(r#"
+            [clients]
+            memory = "preferred"
+        "#).unwrap();
+        assert!(invalid.validate().is_err());
+    }
+}
@@ -1,46 +1,51 @@
 
+use std::sync::Arc;
+
 use log::*;
 
-use crate::config::Config;
+use crate::config::{Config, PinSettings};
 use crate::error::{Result, BenchError};
-use crate::command;
-use super::Action;
-use super::ConnectionInfo;
+use crate::transport::Transport;
+use crate::results::BenchResult;
+use super::{Action, ConnectionInfo, Role};
+use super::log_monitor::{LogMatcher, LogWatcher};
+
+// Relative to the working directory
+const JAR_PATH: &str = "benchmarker/client.jar";
 
 pub struct Client {
     config: Config,
+    transport: Arc<dyn Transport>,
     connection_info: ConnectionInfo,
     vm_args: String,
+    action: Action,
+    pinning: PinSettings,
+    log: LogWatcher
 }
 
 impl Client {
-    pub fn new(config: Config, connection_info: ConnectionInfo, vm_args: String) -> Client {
-        Client {
+    pub fn new(config: Config, transport: Arc<dyn Transport>,
+            connection_info: ConnectionInfo, vm_args: String,
+            action: Action) -> Result<Client> {
+        let log = LogWatcher::new(
+            LogMatcher::new(&config.patterns, Role::Client, action)?);
+        let pinning = config.pinning.settings(Role::Client, connection_info.id);
+
+        Ok(Client {
             config,
+            transport,
             connection_info,
-            vm_args
-        }
-    }
-
-    pub fn send_bench_dir(&self) -> Result<()> {
-        command::scp_to(
-            true,
-            &self.config.system.user_name,
-            &self.connection_info.ip,
-            "benchmarker",
-            &self.config.system.remote_work_dir
-        )?;
-        Ok(())
+            vm_args,
+            action,
+            pinning,
+            log
+        })
     }
 
     pub fn clean_previous_results(&self) -> Result<()> {
         let cmd = format!("rm -r {}",
             self.result_path());
-        let result = command::ssh(
-            &self.config.system.user_name,
-            &self.connection_info.ip,
-            &cmd
-        );
+        let result = self.transport.exec(&self.connection_info.ip, &cmd);
         match result {
             Err(BenchError::CommandFailedOnRemote(_, _, 1, _)) =>
                     debug!("No previous results are found on '{}'", self.connection_info.ip),
@@ -50,54 +55,57 @@ impl Client {
         Ok(())
     }
 
-    pub fn start(&self, action: Action) -> Result<()> {
+    pub fn start(&self) -> Result<()> {
         debug!("Starting client {}...", self.id());
-        // [client id] [action]
-        let prog_args = format!("{} {}",
-            self.connection_info.id, action.as_int());
-        let cmd = format!("{} {} -jar {} {} > {} 2>&1 &",
-            self.config.jdk.remote_java_bin,
-            self.vm_args,
-            self.jar_path(),
-            prog_args,
-            self.log_path()
+        let java_cmd = format!("{}{} {}",
+            self.pinning.command_prefix(),
+            self.config.java_bin(self.ip()),
+            self.vm_args
         );
-        command::ssh(
-            &self.config.system.user_name,
-            &self.connection_info.ip,
-            &cmd
-        )?;
+        let cmd = super::start_command(self.work_dir(), &java_cmd, JAR_PATH,
+            &self.prog_args(), &self.log_filename());
+        self.transport.exec(&self.connection_info.ip, &cmd)?;
+        self.log.start(self.transport.clone(), &self.connection_info.ip,
+            &self.log_path());
         debug!("Client {} is running.", self.id());
         Ok(())
     }
 
-    pub fn check_for_finished(&self, action: Action) -> Result<bool> {
-        let keyword = match action {
-            Action::Loading => "loading procedure finished.",
-            Action::Benchmarking => "benchmark process finished.",
-        };
+    pub fn stop_watching_log(&self) {
+        self.log.stop();
+    }
 
-        if let Ok(output) = self.grep_log("Exception") {
-            return Err(BenchError::Message(
-                format!("Client {} error: {}", self.id(), output)));
-        }
+    pub fn kill(&self) -> Result<()> {
+        debug!("Killing client {}...", self.id());
+        self.signal("TERM")
+    }
 
-        if let Ok(output) = self.grep_log("error") {
-            return Err(BenchError::Message(
-                format!("Client {} error: {}", self.id(), output)));
-        }
+    // The JVM prints the stacks of all the threads to its log
+    pub fn dump_threads(&self) -> Result<()> {
+        debug!("Dumping the threads of client {}...", self.id());
+        self.signal("QUIT")
+    }
 
-        if let Ok(output) = self.grep_log("SEVERE") {
-            return Err(BenchError::Message(
-                format!("Client {} error: {}", self.id(), output)));
-        }
+    pub fn is_running(&self) -> Result<bool> {
+        super::is_process_running(self.transport.as_ref(),
+            &self.connection_info.ip, JAR_PATH, &self.prog_args())
+    }
 
-        match self.grep_log(keyword) {
-            Ok(_) => Ok(true),
-            Err(BenchError::CommandFailedOnRemote(_, _, 1, _)) =>
-                Ok(false),
-            Err(e) => Err(e)
+    pub fn pull_log(&self, dest: &str) -> Result<()> {
+        self.transport.download(
+            false,
+            &self.connection_info.ip,
+            &self.log_path(),
+            dest
+        )
+    }
+
+    pub fn check_for_finished(&self) -> Result<bool> {
+        if let Some(line) = self.log.monitor().error() {
+            return Err(BenchError::Message(
+                format!("Client {} error: {}", self.id(), line)));
         }
+        Ok(self.log.monitor().is_finished())
     }
 
     pub fn pull_csv(&self, dest: &str) -> Result<()> {
@@ -107,9 +115,8 @@ impl Client {
         // Pull the csv file
         let remote_result_path = format!("{}/{}",
             self.result_path(), filename);
-        command::scp_from(
+        self.transport.download(
             false,
-            &self.config.system.user_name,
             &self.connection_info.ip,
             &remote_result_path,
             dest
@@ -117,25 +124,13 @@ impl Client {
         Ok(())
     }
 
-    pub fn get_total_throughput(&self) -> Result<u32> {
-        let cmd = format!("grep 'TOTAL' {}/*-{}.txt",
+    pub fn get_result(&self) -> Result<BenchResult> {
+        // The id must not be the end of another id
+        let cmd = format!("cat {}/*[!0-9]-{}.txt",
             self.result_path(), self.id()
         );
-        let output = command::ssh(
-            &self.config.system.user_name,
-            &self.connection_info.ip,
-            &cmd
-        )?;
-        // Output should be 'TOTAL - committed: XXXX, aborted: XXXX, avg latency: XXX ms'
-        let start = output.find("committed")
-            .ok_or(BenchError::Message(
-                format!("cannot parse result file: {}", output)
-            ))? + 11;
-        let end = output[start ..].find("aborted")
-            .ok_or(BenchError::Message(
-                format!("cannot parse result file: {}", output)
-            ))? + start - 2;
-        Ok(output[start..end].parse()?)
+        let output = self.transport.exec(&self.connection_info.ip, &cmd)?;
+        BenchResult::parse(&output)
     }
 
     pub fn id(&self) -> usize {
@@ -146,46 +141,41 @@ impl Client {
         &self.connection_info.ip
     }
 
-    fn jar_path(&self) -> String {
-        format!("{}/benchmarker/client.jar",
-            &self.config.system.remote_work_dir
-        )
+    pub fn action(&self) -> Action {
+        self.action
+    }
+
+    fn signal(&self, signal: &str) -> Result<()> {
+        super::signal_process(self.transport.as_ref(), &self.connection_info.ip,
+            JAR_PATH, &self.prog_args(), signal)
+    }
+
+    // [client id] [action]
+    fn prog_args(&self) -> String {
+        format!("{} {}", self.connection_info.id, self.action.as_int())
+    }
+
+    fn work_dir(&self) -> &str {
+        self.config.work_dir(&self.connection_info.ip)
     }
 
     fn log_path(&self) -> String {
-        format!("{}/client-{}.log",
-            &self.config.system.remote_work_dir,
-            self.connection_info.id
-        )
+        format!("{}/{}", self.work_dir(), self.log_filename())
     }
 
-    fn result_path(&self) -> String {
-        format!("{}/results",
-            &self.config.system.remote_work_dir
-        )
+    fn log_filename(&self) -> String {
+        format!("client-{}.log", self.connection_info.id)
     }
 
-    fn grep_log(&self, keyword: &str) -> Result<String> {
-        let cmd = format!("grep '{}' {}",
-            keyword,
-            self.log_path()
-        );
-        let output = command::ssh(
-            &self.config.system.user_name,
-            &self.connection_info.ip,
-            &cmd
-        )?;
-        Ok(output)
+    fn result_path(&self) -> String {
+        format!("{}/results", self.work_dir())
     }
 
     fn grep_csv_filename(&self) -> Result<String> {
-        let cmd = format!("ls {} | grep '{}[.]csv'",
+        let cmd = format!("ls {} | grep -- '-{}[.]csv$'",
             self.result_path(), self.id());
-        let filename = command::ssh(
-            &self.config.system.user_name,
-            self.ip(),
-            &cmd
-        )?;
+        let output = self.transport.exec(self.ip(), &cmd)?;
+        let filename = output.trim().to_owned();
 
         if filename.is_empty() {
             return Err(BenchError::Message(
@@ -194,4 +184,34 @@ impl Client {
 
         Ok(filename)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::transport::LocalTransport;
+    use crate::test_utils::{test_config, temp_path};
+
+    #[test]
+    fn test_result_files() {
+        let root = temp_path("results");
+        let result_dir = root.join("10.0.0.3/work/results");
+        fs::create_dir_all(&result_dir).unwrap();
+        for id in &[1, 10, 11] {
+            fs::write(result_dir.join(format!("tpcc-{}.txt", id)), format!(
+                "TOTAL - committed: {}, aborted: 0, avg latency: 1 ms", id)).unwrap();
+            fs::write(result_dir.join(format!("tpcc-{}.csv", id)), "").unwrap();
+        }
+        let transport: Arc<dyn Transport> = Arc::new(LocalTransport::new(&root));
+
+        for id in &[1, 10] {
+            let conn = ConnectionInfo { id: *id, ip: "10.0.0.3".to_owned(), port: 30000 };
+            let client = Client::new(test_config(), transport.clone(), conn,
+                "".to_owned(), Action::Benchmarking).unwrap();
+            assert_eq!(client.get_result().unwrap().total.committed, *id as u32);
+            assert_eq!(client.grep_csv_filename().unwrap(), format!("tpcc-{}.csv", id));
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
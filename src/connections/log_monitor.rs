@@ -0,0 +1,366 @@
+use std::io::{BufRead, BufReader, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use log::*;
+use regex::Regex;
+
+use crate::error::{Result, BenchError};
+use crate::config::{Patterns, PatternSet};
+use crate::transport::Transport;
+use super::{Action, Role};
+
+const DEFAULT_ERRORS: [&str; 3] = ["Exception", "error", "SEVERE"];
+const DEFAULT_READY: &str = "ElaSQL server ready";
+const DEFAULT_LOADING_FINISH: &str = "loading procedure finished.";
+const DEFAULT_BENCHMARKING_FINISH: &str = "benchmark process finished.";
+
+// The compiled patterns for the log of a process
+#[derive(Debug)]
+pub struct LogMatcher {
+    error: Vec<Regex>,
+    allow: Vec<Regex>,
+    ready: Regex,
+    finish: Regex
+}
+
+impl LogMatcher {
+    pub fn new(patterns: &Patterns, role: Role, action: Action) -> Result<LogMatcher> {
+        let role_patterns = match role {
+            Role::Server => &patterns.server,
+            Role::Sequencer => &patterns.sequencer,
+            Role::Client => &patterns.client
+        };
+        let action_set = |set: &'_ crate::config::ActionPatterns| match action {
+            Action::Loading => set.loading.clone(),
+            Action::Benchmarking => set.benchmarking.clone()
+        };
+
+        // The latter ones are more specific
+        let mut set = PatternSet {
+            error: Some(DEFAULT_ERRORS.iter().map(|p| p.to_string()).collect()),
+            allow: Some(Vec::new()),
+            ready: Some(DEFAULT_READY.to_owned()),
+            finish: Some(match action {
+                Action::Loading => DEFAULT_LOADING_FINISH,
+                Action::Benchmarking => DEFAULT_BENCHMARKING_FINISH
+            }.to_owned())
+        };
+        for overrides in [&patterns.common.common, &action_set(&patterns.common),
+                &role_patterns.common, &action_set(role_patterns)] {
+            override_set(&mut set, overrides);
+        }
+
+        Ok(LogMatcher {
+            error: compile_all(set.error.as_ref().unwrap())?,
+            allow: compile_all(set.allow.as_ref().unwrap())?,
+            ready: compile(set.ready.as_ref().unwrap())?,
+            finish: compile(set.finish.as_ref().unwrap())?
+        })
+    }
+
+    // Output: the line reporting an error
+    pub fn find_error<'a>(&self, lines: &[&'a str]) -> Option<&'a str> {
+        lines.iter().find(|line|
+            self.error.iter().any(|p| p.is_match(line))
+                && !self.allow.iter().any(|p| p.is_match(line))
+        ).copied()
+    }
+
+    pub fn is_ready(&self, lines: &[&str]) -> bool {
+        lines.iter().any(|line| self.ready.is_match(line))
+    }
+
+    pub fn is_finished(&self, lines: &[&str]) -> bool {
+        lines.iter().any(|line| self.finish.is_match(line))
+    }
+}
+
+// Checks the log of a process piece by piece as it grows. Only
+// the complete lines are checked.
+#[derive(Debug)]
+pub struct LogMonitor {
+    matcher: LogMatcher,
+    state: Mutex<LogState>
+}
+
+#[derive(Debug, Default)]
+struct LogState {
+    // The bytes that have been read
+    offset: usize,
+    // The last line without a line break
+    pending: String,
+    ready: bool,
+    finished: bool,
+    // The first line reporting an error
+    error: Option<String>
+}
+
+impl LogMonitor {
+    pub fn new(matcher: LogMatcher) -> LogMonitor {
+        LogMonitor {
+            matcher,
+            state: Mutex::new(LogState::default())
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.state.lock().unwrap().offset
+    }
+
+    // Reads the content appended to the log
+    pub fn feed(&self, bytes: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        state.offset += bytes.len();
+        state.pending.push_str(&String::from_utf8_lossy(bytes));
+
+        let (complete, pending) = match state.pending.rfind('\n') {
+            Some(pos) => (state.pending[.. pos].to_owned(),
+                state.pending[pos + 1 ..].to_owned()),
+            None => return
+        };
+        state.pending = pending;
+
+        let lines: Vec<&str> = complete.lines().collect();
+        if self.matcher.is_ready(&lines) {
+            state.ready = true;
+        }
+        if self.matcher.is_finished(&lines) {
+            state.finished = true;
+        }
+        if state.error.is_none() {
+            state.error = self.matcher.find_error(&lines).map(|l| l.to_owned());
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.state.lock().unwrap().ready
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.lock().unwrap().finished
+    }
+
+    pub fn error(&self) -> Option<String> {
+        self.state.lock().unwrap().error.clone()
+    }
+}
+
+// Follows the log of a process through one stream that stays open
+// until `stop` is called, and feeds what it reads to the monitor.
+// The stream is reopened from the last offset if it breaks.
+pub struct LogWatcher {
+    shared: Arc<WatcherState>
+}
+
+struct WatcherState {
+    monitor: LogMonitor,
+    // Closing it stops the remote command
+    input: Mutex<Option<Box<dyn Write + Send>>>,
+    stopped: AtomicBool
+}
+
+impl LogWatcher {
+    pub fn new(matcher: LogMatcher) -> LogWatcher {
+        LogWatcher {
+            shared: Arc::new(WatcherState {
+                monitor: LogMonitor::new(matcher),
+                input: Mutex::new(None),
+                stopped: AtomicBool::new(false)
+            })
+        }
+    }
+
+    pub fn start(&self, transport: Arc<dyn Transport>, ip: &str, log_path: &str) {
+        let shared = self.shared.clone();
+        let ip = ip.to_owned();
+        let log_path = log_path.to_owned();
+        thread::spawn(move || watch(transport.as_ref(), &ip, &log_path, &shared));
+    }
+
+    pub fn stop(&self) {
+        self.shared.stopped.store(true, Ordering::SeqCst);
+        self.shared.input.lock().unwrap().take();
+    }
+
+    pub fn monitor(&self) -> &LogMonitor {
+        &self.shared.monitor
+    }
+}
+
+impl Drop for LogWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn watch(transport: &dyn Transport, ip: &str, log_path: &str,
+        shared: &WatcherState) {
+    while !shared.stopped.load(Ordering::SeqCst) {
+        // tail waits for the log to be created. It is killed once
+        // the input is closed.
+        let cmd = format!("tail -F -c +{} {} 2>/dev/null & read _; kill $!",
+            shared.monitor.offset() + 1, log_path);
+        let stream = match transport.stream(ip, &cmd) {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Failed to follow '{}' on '{}': {}", log_path, ip, e);
+                thread::sleep(Duration::from_secs(1));
+                continue;
+            }
+        };
+        {
+            let mut input = shared.input.lock().unwrap();
+            // Dropping the stream closes its input
+            if shared.stopped.load(Ordering::SeqCst) {
+                break;
+            }
+            *input = Some(stream.input);
+        }
+
+        let mut reader = BufReader::new(stream.output);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => shared.monitor.feed(&line),
+                Err(e) => {
+                    debug!("Failed to read '{}' on '{}': {}", log_path, ip, e);
+                    break;
+                }
+            }
+        }
+        if let Some(mut process) = stream.process {
+            let _ = process.wait();
+        }
+
+        if !shared.stopped.load(Ordering::SeqCst) {
+            debug!("The stream of '{}' on '{}' ends. Reopening it...", log_path, ip);
+            thread::sleep(Duration::from_secs(1));
+        }
+    }
+}
+
+// Checks if all the patterns in the config are valid regexes
+pub fn validate(patterns: &Patterns) -> Result<()> {
+    for role in [Role::Server, Role::Sequencer, Role::Client] {
+        for action in [Action::Loading, Action::Benchmarking] {
+            LogMatcher::new(patterns, role, action)?;
+        }
+    }
+    Ok(())
+}
+
+fn override_set(set: &mut PatternSet, overrides: &PatternSet) {
+    if overrides.error.is_some() {
+        set.error = overrides.error.clone();
+    }
+    if overrides.allow.is_some() {
+        set.allow = overrides.allow.clone();
+    }
+    if overrides.ready.is_some() {
+        set.ready = overrides.ready.clone();
+    }
+    if overrides.finish.is_some() {
+        set.finish = overrides.finish.clone();
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| BenchError::Message(
+        format!("invalid pattern '{}': {}", pattern, e)))
+}
+
+fn compile_all(patterns: &[String]) -> Result<Vec<Regex>> {
+    patterns.iter().map(|p| compile(p)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_path;
+
+    fn parse(toml_str: &str) -> Patterns {
+        toml::from_str(toml_str).unwrap()
+    }
+
+    #[test]
+    fn test_pattern_overrides() {
+        let patterns = parse(r#"
+            allow = ["error_log"]
+
+            [server]
+            ready = "server up"
+
+            [server.loading]
+            error = ["FATAL"]
+        "#);
+
+        let matcher = LogMatcher::new(&patterns, Role::Server, Action::Loading)
+            .unwrap();
+        assert!(matcher.is_ready(&["starting", "server up"]));
+        assert_eq!(matcher.find_error(&["an error", "FATAL: error_log"]), None);
+        assert_eq!(matcher.find_error(&["FATAL: disk"]), Some("FATAL: disk"));
+
+        let matcher = LogMatcher::new(&patterns, Role::Server, Action::Benchmarking)
+            .unwrap();
+        assert_eq!(matcher.find_error(&["an error"]), Some("an error"));
+        assert_eq!(matcher.find_error(&["no error_log"]), None);
+
+        let matcher = LogMatcher::new(&patterns, Role::Sequencer, Action::Loading)
+            .unwrap();
+        assert!(matcher.is_ready(&["ElaSQL server ready"]));
+        assert!(matcher.is_finished(&["loading procedure finished."]));
+        assert!(!matcher.is_finished(&["benchmark process finished."]));
+
+        assert!(validate(&parse(r#"error = ["("]"#)).is_err());
+    }
+
+    #[test]
+    fn test_feed_log() {
+        let matcher = LogMatcher::new(&Patterns::default(), Role::Client,
+            Action::Benchmarking).unwrap();
+        let monitor = LogMonitor::new(matcher);
+
+        monitor.feed(b"starting\nbenchmark process fin");
+        assert!(!monitor.is_finished());
+        monitor.feed(b"ished.\nSEVERE: disk");
+        assert!(monitor.is_finished());
+        assert_eq!(monitor.error(), None);
+        monitor.feed(b" full\n");
+        assert_eq!(monitor.error(), Some("SEVERE: disk full".to_owned()));
+        assert_eq!(monitor.offset(), 55);
+    }
+
+    #[test]
+    fn test_watch_log() {
+        use std::fs;
+        use crate::transport::LocalTransport;
+
+        let root = temp_path("watch");
+        let transport: Arc<dyn Transport> = Arc::new(LocalTransport::new(&root));
+        let matcher = LogMatcher::new(&Patterns::default(), Role::Server,
+            Action::Loading).unwrap();
+        let watcher = LogWatcher::new(matcher);
+
+        // The log does not exist yet
+        watcher.start(transport, "127.0.1.1", "server.log");
+        thread::sleep(Duration::from_millis(500));
+        fs::write(root.join("127.0.1.1/server.log"), "ElaSQL server ready\n")
+            .unwrap();
+        for _ in 0 .. 50 {
+            if watcher.monitor().is_ready() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        assert!(watcher.monitor().is_ready());
+        assert_eq!(watcher.monitor().offset(), 20);
+
+        watcher.stop();
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,13 +1,19 @@
 
 mod server;
 mod client;
+mod log_monitor;
 
 pub use server::Server;
 pub use client::Client;
+pub use log_monitor::validate as validate_patterns;
 
-use crate::error::{Result, BenchError};
+use std::collections::{BTreeSet, HashMap};
+
+use log::*;
 
-const INIT_PORT: usize = 30000;
+use crate::error::{Result, BenchError};
+use crate::config::PortRange;
+use crate::transport::Transport;
 
 #[derive(Clone, Copy)]
 pub enum Action {
@@ -24,6 +30,14 @@ impl Action {
     }
 }
 
+// Decides which patterns are used to read the log
+#[derive(Clone, Copy, Debug)]
+pub enum Role {
+    Server,
+    Sequencer,
+    Client
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct ConnectionInfo {
     pub id: usize,
@@ -31,10 +45,43 @@ pub struct ConnectionInfo {
     pub port: usize
 }
 
+/// Gives the processes their ports. The processes on the same machine
+/// never get the same port, whichever roles they have, so the
+/// sequencer, the servers and the clients of a job should share one.
+#[derive(Default)]
+pub struct PortAllocator {
+    used: HashMap<String, BTreeSet<usize>>
+}
+
+impl PortAllocator {
+    pub fn new() -> PortAllocator {
+        PortAllocator::default()
+    }
+
+    /// Takes the lowest port in the range that is free on the machine
+    pub fn allocate(&mut self, ip: &str, range: &PortRange) -> Result<usize> {
+        let used = self.used.entry(ip.to_owned()).or_default();
+        let port = (range.first ..= range.last).find(|port| !used.contains(port))
+            .ok_or_else(|| BenchError::Message(format!(
+                "no free port in [{}, {}] on '{}'", range.first, range.last, ip)))?;
+        used.insert(port);
+        Ok(port)
+    }
+
+    /// Takes the given port on the machine
+    pub fn reserve(&mut self, ip: &str, port: usize) -> Result<()> {
+        if !self.used.entry(ip.to_owned()).or_default().insert(port) {
+            return Err(BenchError::Message(format!(
+                "port {} on '{}' is given to more than one process", port, ip)));
+        }
+        Ok(())
+    }
+}
+
 impl ConnectionInfo {
     pub fn generate_connection_list(ip_list: &Vec<String>,
-        conn_count: usize, max_conn_per_ip: usize)
-        -> Result<Vec<ConnectionInfo>> {
+        conn_count: usize, max_conn_per_ip: usize, ports: &PortRange,
+        allocator: &mut PortAllocator) -> Result<Vec<ConnectionInfo>> {
         
         let mut list = Vec::new();
         let mut id: usize = 0;
@@ -45,7 +92,7 @@ impl ConnectionInfo {
                 list.push(ConnectionInfo {
                     id,
                     ip: ip.to_owned(),
-                    port: INIT_PORT + conn_per_node - 1
+                    port: allocator.allocate(ip, ports)?
                 });
 
                 id += 1;
@@ -57,21 +104,74 @@ impl ConnectionInfo {
             conn_per_node += 1;
             if conn_per_node > max_conn_per_ip {
                 return Err(BenchError::Message(
-                    format!("The number of machines is not enough.")));
+                    "The number of machines is not enough.".to_owned()));
             }
         }
 
         Ok(list)
     }
+}
 
-    pub fn to_string(&self) -> String {
-        format!("{} {} {}", self.id, self.ip, self.port)
+impl std::fmt::Display for ConnectionInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.id, self.ip, self.port)
     }
 }
 
+// Matches the command lines containing the text. The brackets keep
+// the pattern from matching the shell running it.
+pub fn command_pattern(text: &str) -> String {
+    format!("'[{}]{}'", &text[.. 1], &text[1 ..])
+}
+
+// Matches the process started by running the jar with the arguments
+fn process_pattern(jar_path: &str, prog_args: &str) -> String {
+    command_pattern(&format!("{} {}$", jar_path, prog_args))
+}
+
+// Starts the jar in the working directory with the java command and
+// returns at once. The old log is removed so that it will not be read
+// as the new one. The absolute path of the jar tells the process from
+// the processes of other directories.
+fn start_command(work_dir: &str, java_cmd: &str, jar_path: &str,
+        prog_args: &str, log_filename: &str) -> String {
+    // Only the JVM goes to the background. A background subshell would
+    // keep the output of ssh open until the JVM exits.
+    format!("cd {} || exit 1; rm -f {}; {} -jar \"$(pwd -P)\"/{} {} > {} 2>&1 &",
+        work_dir, log_filename, java_cmd, jar_path, prog_args, log_filename)
+}
+
+fn is_process_running(transport: &dyn Transport, ip: &str, jar_path: &str,
+        prog_args: &str) -> Result<bool> {
+    let cmd = format!("pgrep -f {}", process_pattern(jar_path, prog_args));
+    match transport.exec(ip, &cmd) {
+        Ok(_) => Ok(true),
+        Err(BenchError::CommandFailedOnRemote(_, _, 1, _)) => Ok(false),
+        Err(e) => Err(e)
+    }
+}
+
+fn signal_process(transport: &dyn Transport, ip: &str, jar_path: &str,
+        prog_args: &str, signal: &str) -> Result<()> {
+    let cmd = format!("pkill -{} -f {}", signal,
+        process_pattern(jar_path, prog_args));
+    match transport.exec(ip, &cmd) {
+        Err(BenchError::CommandFailedOnRemote(_, _, 1, _)) =>
+            debug!("The process of '{}' on '{}' has already stopped", jar_path, ip),
+        Err(e) => return Err(e),
+        _ => {}
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
+    use std::fs;
+    use std::process::{Command, Stdio};
+    use std::time::{Duration, Instant};
+
     use super::*;
+    use crate::test_utils::temp_path;
 
     #[test]
     fn test_connection_list() {
@@ -80,8 +180,9 @@ mod tests {
             "192.168.1.2".to_owned(),
             "192.168.1.3".to_owned()
         ];
+        let ports = PortRange { first: 30000, last: 30999 };
         let list = ConnectionInfo::generate_connection_list(
-            &ip_list, 5, 2).unwrap();
+            &ip_list, 5, 2, &ports, &mut PortAllocator::new()).unwrap();
 
         let expected = vec![
             ConnectionInfo {
@@ -113,4 +214,75 @@ mod tests {
 
         assert_eq!(&list, &expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_co_located_ports() {
+        let range = PortRange { first: 30000, last: 30999 };
+        let addresses = |list: &[ConnectionInfo]| -> Vec<String> {
+            list.iter().map(|c| format!("{}:{}", c.ip, c.port)).collect()
+        };
+
+        // The sequencer and the first server share a machine, and so do
+        // the second server and a client
+        let mut allocator = PortAllocator::new();
+        let sequencer = allocator.allocate("10.0.0.1", &range).unwrap();
+        let servers = ConnectionInfo::generate_connection_list(
+            &vec!["10.0.0.1".to_owned(), "10.0.0.2".to_owned()], 3, 2,
+            &range, &mut allocator).unwrap();
+        let clients = ConnectionInfo::generate_connection_list(
+            &vec!["10.0.0.2".to_owned(), "10.0.0.3".to_owned()], 2, 1,
+            &range, &mut allocator).unwrap();
+
+        assert_eq!(sequencer, 30000);
+        assert_eq!(addresses(&servers), ["10.0.0.1:30001", "10.0.0.2:30000",
+            "10.0.0.1:30002"]);
+        assert_eq!(addresses(&clients), ["10.0.0.2:30001", "10.0.0.3:30000"]);
+
+        // The roles with their own ranges
+        let mut allocator = PortAllocator::new();
+        let client_range = PortRange { first: 40000, last: 40001 };
+        allocator.allocate("10.0.0.1", &range).unwrap();
+        assert_eq!(allocator.allocate("10.0.0.1", &client_range).unwrap(), 40000);
+        assert_eq!(allocator.allocate("10.0.0.1", &client_range).unwrap(), 40001);
+        assert!(allocator.allocate("10.0.0.1", &client_range).is_err());
+        assert_eq!(allocator.allocate("10.0.0.2", &client_range).unwrap(), 40000);
+    }
+
+    #[test]
+    fn test_signal_process() {
+        use crate::transport::mock::MockTransport;
+
+        let mock = MockTransport::new(|ip, cmd| Err(BenchError::CommandFailedOnRemote(
+            ip.to_owned(), cmd.to_owned(), 1, "".to_owned())));
+        signal_process(&mock, "10.0.0.1", "work/benchmarker/server.jar",
+            "db-1 1", "QUIT").unwrap();
+        assert!(!is_process_running(&mock, "10.0.0.1", "work/benchmarker/server.jar",
+            "db-1 1").unwrap());
+        assert_eq!(mock.records(), vec![
+            "exec 10.0.0.1 pkill -QUIT -f '[w]ork/benchmarker/server.jar db-1 1$'"
+                .to_owned(),
+            "exec 10.0.0.1 pgrep -f '[w]ork/benchmarker/server.jar db-1 1$'"
+                .to_owned()]);
+    }
+
+    #[test]
+    fn test_start_command_returns() {
+        let work_dir = temp_path("start");
+        fs::create_dir_all(&work_dir).unwrap();
+        // Stands for a JVM running for a while
+        let cmd = start_command(work_dir.to_str().unwrap(),
+            "sh -c 'echo started; sleep 10' sh", "server.jar", "db 0",
+            "server-0.log");
+
+        let start = Instant::now();
+        let output = Command::new("bash").arg("-c").arg(&cmd)
+            .stdout(Stdio::piped()).output().unwrap();
+        assert!(output.status.success());
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(fs::read_to_string(work_dir.join("server-0.log")).unwrap(),
+            "started\n");
+        fs::remove_dir_all(&work_dir).unwrap();
+    }
+}
@@ -1,27 +1,36 @@
 
+use std::sync::Arc;
+
 use log::*;
 
-use crate::config::Config;
+use crate::config::{Config, PinSettings};
 use crate::error::{Result, BenchError};
-use crate::command;
-use super::ConnectionInfo;
+use crate::transport::Transport;
+use super::{Action, ConnectionInfo, Role};
+use super::log_monitor::{LogMatcher, LogWatcher};
+
+// Relative to the working directory
+const JAR_PATH: &str = "benchmarker/server.jar";
 
 pub struct Server {
     config: Config,
+    transport: Arc<dyn Transport>,
     connection_info: ConnectionInfo,
     proc_name: String,
     db_name: String,
     vm_args: String,
-    is_sequencer: bool
+    is_sequencer: bool,
+    pinning: PinSettings,
+    log: LogWatcher
 }
 
 impl Server {
-    pub fn new(config: Config, connection_info: ConnectionInfo,
-        db_name: String, vm_args: String,
-        is_sequencer: bool) -> Server {
+    pub fn new(config: Config, transport: Arc<dyn Transport>,
+        connection_info: ConnectionInfo, db_name: String, vm_args: String,
+        is_sequencer: bool, action: Action) -> Result<Server> {
         
         let proc_name = if is_sequencer {
-            format!("sequencer")
+            "sequencer".to_owned()
         } else {
             format!("server {}", connection_info.id)
         };
@@ -30,37 +39,32 @@ impl Server {
         } else {
             format!("{}-{}", db_name, connection_info.id)
         };
+        let role = if is_sequencer {
+            Role::Sequencer
+        } else {
+            Role::Server
+        };
+        let log = LogWatcher::new(
+            LogMatcher::new(&config.patterns, role, action)?);
+        let pinning = config.pinning.settings(role, connection_info.id);
 
-        Server {
+        Ok(Server {
             config,
+            transport,
             connection_info,
             proc_name,
             db_name,
             vm_args,
-            is_sequencer
-        }
-    }
-
-    pub fn send_bench_dir(&self) -> Result<()> {
-        debug!("Sending benchmarker to {}...", self.proc_name);
-        command::scp_to(
-            true,
-            &self.config.system.user_name,
-            &self.connection_info.ip,
-            "benchmarker",
-            &self.config.system.remote_work_dir
-        )?;
-        Ok(())
+            is_sequencer,
+            pinning,
+            log
+        })
     }
 
     pub fn delete_db_dir(&self) -> Result<()> {
         let cmd = format!("rm -rf {}",
             self.db_path());
-        let result = command::ssh(
-            &self.config.system.user_name,
-            &self.connection_info.ip,
-            &cmd
-        );
+        let result = self.transport.exec(&self.connection_info.ip, &cmd);
         match result {
             Err(BenchError::CommandFailedOnRemote(_, _, 1, _)) =>
                     debug!("No previous database is found on '{}'", self.connection_info.ip),
@@ -74,11 +78,7 @@ impl Server {
         debug!("Deleting backup dir on {}...", self.proc_name);
         let cmd = format!("rm -rf {}",
             self.backup_db_path());
-        let result = command::ssh(
-            &self.config.system.user_name,
-            &self.connection_info.ip,
-            &cmd
-        );
+        let result = self.transport.exec(&self.connection_info.ip, &cmd);
         match result {
             Err(BenchError::CommandFailedOnRemote(_, _, 1, _)) =>
                     debug!("No backup database is found on '{}'", self.connection_info.ip),
@@ -99,11 +99,7 @@ impl Server {
             self.db_path(),
             self.backup_db_path()
         );
-        command::ssh(
-            &self.config.system.user_name,
-            &self.connection_info.ip,
-            &cmd
-        )?;
+        self.transport.exec(&self.connection_info.ip, &cmd)?;
         Ok(())
     }
 
@@ -120,64 +116,64 @@ impl Server {
             self.backup_db_path(),
             self.db_path()
         );
-        command::ssh(
-            &self.config.system.user_name,
-            &self.connection_info.ip,
-            &cmd
-        )?;
+        self.transport.exec(&self.connection_info.ip, &cmd)?;
         Ok(())
     }
 
     pub fn start(&self) -> Result<()> {
         debug!("Starting {}...", self.proc_name);
-        // [db name] [server id] ([is sequencer])
-        let prog_args = if self.is_sequencer {
-            format!("{} {} 1", self.db_name, self.connection_info.id)
-        } else {
-            format!("{} {}", self.db_name, self.connection_info.id)
-        };
-        let cmd = format!("{} {} -jar {} {} > {} 2>&1 &",
-            self.config.jdk.remote_java_bin,
-            self.vm_args,
-            self.jar_path(),
-            prog_args,
-            self.log_path()
+        let java_cmd = format!("{}{} {}",
+            self.pinning.command_prefix(),
+            self.config.java_bin(self.ip()),
+            self.vm_args
         );
-        command::ssh(
-            &self.config.system.user_name,
-            &self.connection_info.ip,
-            &cmd
-        )?;
+        let cmd = super::start_command(self.work_dir(), &java_cmd, JAR_PATH,
+            &self.prog_args(), &self.log_filename());
+        self.transport.exec(&self.connection_info.ip, &cmd)?;
+        self.log.start(self.transport.clone(), &self.connection_info.ip,
+            &self.log_path());
         Ok(())
     }
 
-    pub fn check_for_ready(&self) -> Result<bool> {
-        self.check_for_error()?;
+    pub fn stop_watching_log(&self) {
+        self.log.stop();
+    }
 
-        match self.grep_log("ElaSQL server ready") {
-            Ok(_) => Ok(true),
-            Err(BenchError::CommandFailedOnRemote(_, _, 1, _)) =>
-                Ok(false),
-            Err(e) => Err(e)
-        }
+    pub fn kill(&self) -> Result<()> {
+        debug!("Killing {}...", self.proc_name);
+        self.signal("TERM")
     }
 
-    pub fn check_for_error(&self) -> Result<()> {
-        if let Ok(output) = self.grep_log("Exception") {
-            return Err(BenchError::Message(
-                format!("Server {} error: {}", self.id(), output)));
-        }
+    // The JVM prints the stacks of all the threads to its log
+    pub fn dump_threads(&self) -> Result<()> {
+        debug!("Dumping the threads of {}...", self.proc_name);
+        self.signal("QUIT")
+    }
 
-        if let Ok(output) = self.grep_log("error") {
-            return Err(BenchError::Message(
-                format!("Server {} error: {}", self.id(), output)));
-        }
+    pub fn is_running(&self) -> Result<bool> {
+        super::is_process_running(self.transport.as_ref(),
+            &self.connection_info.ip, JAR_PATH, &self.prog_args())
+    }
+
+    pub fn pull_log(&self, dest: &str) -> Result<()> {
+        self.transport.download(
+            false,
+            &self.connection_info.ip,
+            &self.log_path(),
+            dest
+        )
+    }
 
-        if let Ok(output) = self.grep_log("SEVERE") {
+    pub fn check_for_ready(&self) -> Result<bool> {
+        self.check_for_error()?;
+        Ok(self.log.monitor().is_ready())
+    }
+
+    pub fn check_for_error(&self) -> Result<()> {
+        if let Some(line) = self.log.monitor().error() {
             return Err(BenchError::Message(
-                format!("Server {} error: {}", self.id(), output)));
+                format!("Server {} error: {}", self.id(), line)));
         }
-
         Ok(())
     }
 
@@ -193,49 +189,47 @@ impl Server {
         self.is_sequencer
     }
 
+    fn work_dir(&self) -> &str {
+        self.config.work_dir(&self.connection_info.ip)
+    }
+
     fn db_path(&self) -> String {
         format!("{}/databases/{}",
-            &self.config.system.remote_work_dir,
+            self.work_dir(),
             &self.db_name
         )
     }
 
     fn backup_db_path(&self) -> String {
         format!("{}/databases/{}-backup",
-            &self.config.system.remote_work_dir,
+            self.work_dir(),
             &self.db_name
         )
     }
 
-    fn jar_path(&self) -> String {
-        format!("{}/benchmarker/server.jar",
-            &self.config.system.remote_work_dir
-        )
+    fn signal(&self, signal: &str) -> Result<()> {
+        super::signal_process(self.transport.as_ref(), &self.connection_info.ip,
+            JAR_PATH, &self.prog_args(), signal)
     }
 
-    fn log_path(&self) -> String {
+    // [db name] [server id] ([is sequencer])
+    fn prog_args(&self) -> String {
         if self.is_sequencer {
-            format!("{}/server-seq.log",
-                &self.config.system.remote_work_dir
-            )
+            format!("{} {} 1", self.db_name, self.connection_info.id)
         } else {
-            format!("{}/server-{}.log",
-                &self.config.system.remote_work_dir,
-                &self.connection_info.id
-            )
+            format!("{} {}", self.db_name, self.connection_info.id)
         }
     }
 
-    fn grep_log(&self, keyword: &str) -> Result<String> {
-        let cmd = format!("grep '{}' {}",
-            keyword,
-            self.log_path()
-        );
-        let output = command::ssh(
-            &self.config.system.user_name,
-            &self.connection_info.ip,
-            &cmd
-        )?;
-        Ok(output)
+    fn log_path(&self) -> String {
+        format!("{}/{}", self.work_dir(), self.log_filename())
     }
-}
\ No newline at end of file
+
+    fn log_filename(&self) -> String {
+        if self.is_sequencer {
+            "server-seq.log".to_owned()
+        } else {
+            format!("server-{}.log", self.connection_info.id)
+        }
+    }
+}
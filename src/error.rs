@@ -17,6 +17,10 @@ pub enum BenchError {
     CommandKilledBySingal(String),
     // (path)
     FileNotFound(String),
+    // Stopped by Ctrl-C
+    Interrupted,
+    // (what did not happen in time)
+    Timeout(String),
 
     // Wrapper
     ParseUtf8Error(std::string::FromUtf8Error),
@@ -95,7 +99,7 @@ impl From<csv::Error> for BenchError {
 }
 
 impl BenchError {
-    pub fn as_remote_if_possible(self, ip: &str) -> Self {
+    pub fn into_remote_if_possible(self, ip: &str) -> Self {
         match self {
             BenchError::NoSuchCommand(cmd) =>
                 BenchError::NoSuchCommandOnRemote(ip.to_owned(), cmd),
@@ -116,8 +120,16 @@ impl std::fmt::Display for BenchError {
             BenchError::CommandFailedOnRemote(ip, cmd, code, stderr) => write!(f,
                 "command '{}' fails on {} with return code {}.\nError message: {}",
                 cmd, ip, code, stderr),
+            BenchError::NoSuchCommand(cmd) => write!(f,
+                "no such command: '{}'", cmd),
+            BenchError::NoSuchCommandOnRemote(ip, cmd) => write!(f,
+                "no such command on {}: '{}'", ip, cmd),
+            BenchError::CommandKilledBySingal(cmd) => write!(f,
+                "command '{}' is killed by a signal", cmd),
             BenchError::FileNotFound(path) => write!(f,
                 "file not found: '{}'", path),
+            BenchError::Interrupted => write!(f, "interrupted by the user"),
+            BenchError::Timeout(s) => write!(f, "timed out: {}", s),
             BenchError::Message(s) => write!(f, "{}", s),
             e => write!(f, "{:?}", e)
         }
@@ -135,6 +147,7 @@ impl Error for BenchError {
             BenchError::ParseJsonError(e) => Some(e),
             BenchError::ParesPropertiesError(e) => Some(e),
             BenchError::IoError(e) => Some(e),
+            BenchError::CsvError(e) => Some(e),
             _ => None,
         }
     }
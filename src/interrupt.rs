@@ -0,0 +1,40 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::{Result, BenchError};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_signal(_: libc::c_int) {
+    // The second Ctrl-C exits immediately in case the cleanup gets stuck
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        unsafe { libc::_exit(130) };
+    }
+}
+
+/// Catches SIGINT and SIGTERM instead of exiting, so that the running
+/// job can stop its remote processes. The waiting loops should call
+/// `check` to find out if they have been interrupted.
+pub fn install_handler() {
+    let handler = handle_signal as extern "C" fn(libc::c_int);
+    unsafe {
+        libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, handler as libc::sighandler_t);
+    }
+}
+
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+pub fn check() -> Result<()> {
+    if is_interrupted() {
+        Err(BenchError::Interrupted)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub fn set_interrupted(interrupted: bool) {
+    INTERRUPTED.store(interrupted, Ordering::SeqCst);
+}
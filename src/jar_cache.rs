@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::Path;
+
+use log::*;
+use sha2::{Sha256, Digest};
+
+use crate::error::{Result, BenchError};
+use crate::transport::Transport;
+
+const CACHE_DIR: &str = "jar-cache";
+const JAR_FILENAMES: [&str; 2] = ["server.jar", "client.jar"];
+
+/// A jar identified by the SHA-256 of its content. The machines keep
+/// the jars under `[remote work dir]/jar-cache/[hash].jar`, so the same
+/// jar is only sent once even if it is used by many jobs.
+#[derive(Debug, Clone)]
+pub struct CachedJar {
+    // The name used by the benchmarker, e.g. server.jar
+    pub filename: String,
+    pub local_path: String,
+    pub hash: String,
+    pub size: u64
+}
+
+impl CachedJar {
+    fn from_file(filename: &str, path: &Path) -> Result<CachedJar> {
+        if !path.is_file() {
+            return Err(BenchError::FileNotFound(path.display().to_string()));
+        }
+        let content = fs::read(path)?;
+        let hash = format!("{:x}", Sha256::digest(&content));
+
+        Ok(CachedJar {
+            filename: filename.to_owned(),
+            local_path: path.to_str().unwrap().to_owned(),
+            hash,
+            size: content.len() as u64
+        })
+    }
+
+    fn cache_path(&self, remote_work_dir: &str) -> String {
+        format!("{}/{}/{}.jar", remote_work_dir, CACHE_DIR, self.hash)
+    }
+}
+
+pub fn read_jars(jar_dir: &Path) -> Result<Vec<CachedJar>> {
+    JAR_FILENAMES.iter()
+        .map(|filename| CachedJar::from_file(filename, &jar_dir.join(filename)))
+        .collect()
+}
+
+// Output: whether the jar is sent
+pub fn ensure_cached(transport: &dyn Transport, ip: &str, remote_work_dir: &str,
+        jar: &CachedJar) -> Result<bool> {
+    let cache_path = jar.cache_path(remote_work_dir);
+
+    // Touch the cached jar so that pruning knows it is recently used
+    let cmd = format!("test -e {0} && touch {0}", cache_path);
+    match transport.exec(ip, &cmd) {
+        Ok(_) => {
+            debug!("{} ({}) is cached on '{}'", jar.filename, &jar.hash[.. 8], ip);
+            return Ok(false);
+        },
+        Err(BenchError::CommandFailedOnRemote(_, _, 1, _)) => {},
+        Err(e) => return Err(e)
+    }
+
+    debug!("Sending {} ({}) to '{}'...", jar.filename, &jar.hash[.. 8], ip);
+    transport.exec(ip, &format!("mkdir -p {}/{}", remote_work_dir, CACHE_DIR))?;
+    // Upload to a temporary file so that an interrupted upload
+    // will not be taken as cached
+    let tmp_path = format!("{}.tmp", cache_path);
+    transport.upload(false, ip, &jar.local_path, &tmp_path)?;
+    transport.exec(ip, &format!("mv {} {}", tmp_path, cache_path))?;
+    Ok(true)
+}
+
+// Makes the jar in the benchmarker dir point to the cached one.
+// The benchmarker dir must be right under the remote work dir.
+pub fn link(transport: &dyn Transport, ip: &str, remote_bench_dir: &str,
+        jar: &CachedJar) -> Result<()> {
+    let cmd = format!("ln -sf ../{}/{}.jar {}/{}",
+        CACHE_DIR, jar.hash, remote_bench_dir, jar.filename);
+    transport.exec(ip, &cmd)?;
+    Ok(())
+}
+
+/// Removes the cached jars on the machine except the `keep` most recently
+/// used ones and the ones used by the current benchmarker dir.
+///
+/// Returns: the number of removed jars
+pub fn prune(transport: &dyn Transport, ip: &str, remote_work_dir: &str,
+        remote_bench_dir: &str, keep: usize) -> Result<usize> {
+    let cache_dir = format!("{}/{}", remote_work_dir, CACHE_DIR);
+
+    // Newest first
+    let output = transport.exec(ip,
+        &format!("mkdir -p {0} && ls -t {0}", cache_dir))?;
+    let cached: Vec<&str> = output.split_whitespace()
+        .filter(|f| f.ends_with(".jar"))
+        .collect();
+
+    let links: Vec<String> = JAR_FILENAMES.iter()
+        .map(|f| format!("{}/{}", remote_bench_dir, f))
+        .collect();
+    let output = transport.exec(ip,
+        &format!("readlink {} 2>/dev/null; true", links.join(" ")))?;
+    let in_use: Vec<&str> = output.split_whitespace()
+        .filter_map(|path| path.rsplit('/').next())
+        .collect();
+
+    let evicted: Vec<String> = cached.into_iter()
+        .skip(keep)
+        .filter(|f| !in_use.contains(f))
+        .map(|f| format!("{}/{}", cache_dir, f))
+        .collect();
+    if !evicted.is_empty() {
+        transport.exec(ip, &format!("rm -f {}", evicted.join(" ")))?;
+    }
+    Ok(evicted.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_prune() {
+        let mock = MockTransport::new(|_, cmd| {
+            if cmd.starts_with("mkdir") {
+                Ok("d.jar\nc.jar\nb.jar\na.jar\nc.jar.tmp\n".to_owned())
+            } else if cmd.starts_with("readlink") {
+                Ok("../jar-cache/a.jar\n../jar-cache/d.jar\n".to_owned())
+            } else {
+                Ok("".to_owned())
+            }
+        });
+
+        let removed = prune(&mock, "10.0.0.1", "work", "work/benchmarker", 1)
+            .unwrap();
+        assert_eq!(removed, 2);
+        assert!(mock.records().contains(&"exec 10.0.0.1 rm -f \
+            work/jar-cache/c.jar work/jar-cache/b.jar".to_owned()));
+    }
+}
@@ -0,0 +1,188 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Serialize, Deserialize};
+use chrono::prelude::*;
+
+use crate::error::{Result, BenchError};
+
+const LEDGER_FILENAME: &str = "jobs.csv";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Finished,
+    Failed,
+    TimedOut,
+    Interrupted
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JobRecord {
+    pub job_id: usize,
+    pub trial: usize,
+    pub param_hash: String,
+    pub status: JobStatus,
+    pub throughput: Option<u32>,
+    pub start_time: String,
+    pub end_time: Option<String>
+}
+
+/// Keeps the status of each trial of the jobs of a benchmark campaign
+/// in the report directory, so that an interrupted campaign can be
+/// resumed later.
+#[derive(Debug)]
+pub struct JobLedger {
+    path: PathBuf,
+    records: Vec<JobRecord>
+}
+
+impl JobLedger {
+    pub fn create(report_dir: &Path) -> Result<JobLedger> {
+        let ledger = JobLedger {
+            path: report_dir.join(LEDGER_FILENAME),
+            records: Vec::new()
+        };
+        ledger.save()?;
+        Ok(ledger)
+    }
+
+    pub fn open(report_dir: &Path) -> Result<JobLedger> {
+        let path = report_dir.join(LEDGER_FILENAME);
+        if !path.is_file() {
+            return Err(BenchError::FileNotFound(path.display().to_string()));
+        }
+
+        let mut reader = csv::Reader::from_path(&path)?;
+        let mut records = Vec::new();
+        for record in reader.deserialize() {
+            records.push(record?);
+        }
+
+        Ok(JobLedger {
+            path,
+            records
+        })
+    }
+
+    pub fn get(&self, job_id: usize, trial: usize) -> Option<&JobRecord> {
+        self.records.iter().find(|r| r.job_id == job_id && r.trial == trial)
+    }
+
+    pub fn finished_throughputs(&self, job_id: usize) -> Vec<u32> {
+        self.records.iter()
+            .filter(|r| r.job_id == job_id && r.status == JobStatus::Finished)
+            .filter_map(|r| r.throughput)
+            .collect()
+    }
+
+    pub fn start_job(&mut self, job_id: usize, trial: usize,
+            param_hash: &str) -> Result<()> {
+        let record = JobRecord {
+            job_id,
+            trial,
+            param_hash: param_hash.to_owned(),
+            status: JobStatus::Running,
+            throughput: None,
+            start_time: Local::now().to_rfc3339(),
+            end_time: None
+        };
+
+        match self.records.iter_mut()
+                .find(|r| r.job_id == job_id && r.trial == trial) {
+            Some(old) => *old = record,
+            None => self.records.push(record)
+        }
+        self.save()
+    }
+
+    pub fn finish_job(&mut self, job_id: usize, trial: usize,
+            throughput: u32) -> Result<()> {
+        self.end_job(job_id, trial, JobStatus::Finished, Some(throughput))
+    }
+
+    pub fn fail_job(&mut self, job_id: usize, trial: usize) -> Result<()> {
+        self.end_job(job_id, trial, JobStatus::Failed, None)
+    }
+
+    pub fn time_out_job(&mut self, job_id: usize, trial: usize) -> Result<()> {
+        self.end_job(job_id, trial, JobStatus::TimedOut, None)
+    }
+
+    pub fn interrupt_job(&mut self, job_id: usize, trial: usize) -> Result<()> {
+        self.end_job(job_id, trial, JobStatus::Interrupted, None)
+    }
+
+    fn end_job(&mut self, job_id: usize, trial: usize, status: JobStatus,
+            throughput: Option<u32>) -> Result<()> {
+        let record = self.records.iter_mut()
+            .find(|r| r.job_id == job_id && r.trial == trial)
+            .ok_or_else(|| BenchError::Message(
+                format!("trial {} of job {} has not started", trial, job_id)))?;
+        record.status = status;
+        record.throughput = throughput;
+        record.end_time = Some(Local::now().to_rfc3339());
+        self.save()
+    }
+
+    // Write to a temporary file first so that a crash during writing
+    // does not corrupt the ledger
+    fn save(&self) -> Result<()> {
+        let tmp_path = self.path.with_extension("csv.tmp");
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(!self.records.is_empty())
+            .from_path(&tmp_path)?;
+        if self.records.is_empty() {
+            writer.write_record(["job_id", "trial", "param_hash", "status",
+                "throughput", "start_time", "end_time"])?;
+        }
+        for record in &self.records {
+            writer.serialize(record)?;
+        }
+        writer.flush()?;
+        fs::rename(tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_path;
+
+    #[test]
+    fn test_reopen_ledger() {
+        let dir = temp_path("ledger");
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut ledger = JobLedger::create(&dir).unwrap();
+        assert!(JobLedger::open(&dir).unwrap().get(0, 0).is_none());
+
+        ledger.start_job(0, 0, "aaa").unwrap();
+        ledger.finish_job(0, 0, 1234).unwrap();
+        ledger.start_job(0, 1, "aaa").unwrap();
+        ledger.fail_job(0, 1).unwrap();
+        ledger.start_job(1, 0, "bbb").unwrap();
+        ledger.time_out_job(1, 0).unwrap();
+        ledger.start_job(1, 1, "bbb").unwrap();
+        ledger.interrupt_job(1, 1).unwrap();
+        ledger.start_job(2, 0, "ccc").unwrap();
+
+        let ledger = JobLedger::open(&dir).unwrap();
+        let job = ledger.get(0, 0).unwrap();
+        assert_eq!(job.status, JobStatus::Finished);
+        assert_eq!(job.param_hash, "aaa");
+        assert_eq!(job.throughput, Some(1234));
+        assert!(job.end_time.is_some());
+        assert_eq!(ledger.get(0, 1).unwrap().status, JobStatus::Failed);
+        assert_eq!(ledger.finished_throughputs(0), vec![1234]);
+        assert_eq!(ledger.get(1, 0).unwrap().status, JobStatus::TimedOut);
+        let job = ledger.get(2, 0).unwrap();
+        assert_eq!(job.status, JobStatus::Running);
+        assert_eq!(job.throughput, None);
+        assert_eq!(ledger.get(1, 1).unwrap().status, JobStatus::Interrupted);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
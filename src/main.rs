@@ -3,17 +3,31 @@ mod error;
 mod config;
 mod command;
 mod preparation;
+mod jar_cache;
 mod subcommands;
 mod parameters;
+mod ledger;
+mod stats;
+mod results;
 mod properties;
 mod connections;
 mod threads;
+mod transport;
+mod interrupt;
+mod parallel;
+mod selection;
+mod topology;
+#[cfg(test)]
+mod test_utils;
 
 use clap::{Arg, ArgMatches, App};
 use log::*;
 
+use std::sync::Arc;
+
 use error::BenchError;
 use config::Config;
+use transport::Transport;
 
 fn main() {
     // Setup the logger
@@ -30,11 +44,17 @@ fn main() {
                             .value_name("FILE")
                             .help("Sets the path to a config file")
                             .takes_value(true))
+                       .arg(Arg::with_name("local")
+                            .long("local")
+                            .help("Runs all the processes on localhost instead of the machines"))
                        .subcommand(subcommands::init_env::get_sub_command())
                        .subcommand(subcommands::load::get_sub_command())
                        .subcommand(subcommands::benchmark::get_sub_command())
                        .subcommand(subcommands::all_execute::get_sub_command())
                        .subcommand(subcommands::pull::get_sub_command())
+                       .subcommand(subcommands::plan::get_sub_command())
+                       .subcommand(subcommands::cache::get_sub_command())
+                       .subcommand(subcommands::doctor::get_sub_command())
                        .get_matches();
     
     match execute(matches) {
@@ -53,19 +73,37 @@ fn set_logger_level() {
 fn execute(matches: ArgMatches) -> Result<(), BenchError> {
      // Read the config
     let config_file_path = matches.value_of("config").unwrap_or("config.toml");
-    let config = Config::from_file(&config_file_path)?;
+    let mut config = Config::from_file(config_file_path)?;
+    if matches.is_present("local") {
+        config.use_local_mode();
+        info!("Running in local mode. The working directories are under '{}'",
+            config.local_root_dir().display());
+    }
+    let transport = transport::create(&config);
+    let result = execute_sub_command(&config, &transport, &matches);
+    transport.close();
+    result
+}
 
+fn execute_sub_command(config: &Config, transport: &Arc<dyn Transport>,
+        matches: &ArgMatches) -> Result<(), BenchError> {
     // Choose action according to the sub command
     if let Some(matches) = matches.subcommand_matches("init-env") {
-        subcommands::init_env::execute(&config, matches)?;
+        subcommands::init_env::execute(config, transport, matches)?;
     } else if let Some(matches) = matches.subcommand_matches("load") {
-        subcommands::load::execute(&config, matches)?;
+        subcommands::load::execute(config, transport, matches)?;
     } else if let Some(matches) = matches.subcommand_matches("bench") {
-        subcommands::benchmark::execute(&config, matches)?;
+        subcommands::benchmark::execute(config, transport, matches)?;
     } else if let Some(matches) = matches.subcommand_matches("all-exec") {
-        subcommands::all_execute::execute(&config, matches)?;
+        subcommands::all_execute::execute(config, transport, matches)?;
     } else if let Some(matches) = matches.subcommand_matches("pull") {
-        subcommands::pull::execute(&config, matches)?;
+        subcommands::pull::execute(config, transport, matches)?;
+    } else if let Some(matches) = matches.subcommand_matches("plan") {
+        subcommands::plan::execute(config, matches)?;
+    } else if let Some(matches) = matches.subcommand_matches("cache") {
+        subcommands::cache::execute(config, transport, matches)?;
+    } else if let Some(matches) = matches.subcommand_matches("doctor") {
+        subcommands::doctor::execute(config, transport, matches)?;
     }
     
     Ok(())
@@ -0,0 +1,166 @@
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::{Arg, ArgMatches};
+use colored::*;
+use log::*;
+
+use crate::error::{Result, BenchError};
+
+const DEFAULT_PARALLELISM: &str = "16";
+
+/// The outcome of running a task on a machine
+#[derive(Debug)]
+pub struct HostOutcome {
+    pub ip: String,
+    pub elapsed: Duration,
+    // Errors cannot be sent across threads, so only the messages are kept
+    pub error: Option<String>
+}
+
+pub fn parallelism_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("PARALLEL")
+        .long("parallel")
+        .short("p")
+        .value_name("N")
+        .takes_value(true)
+        .default_value(DEFAULT_PARALLELISM)
+        .help("The number of machines to work on at the same time")
+}
+
+pub fn get_parallelism(args: &ArgMatches) -> Result<usize> {
+    let limit: usize = args.value_of("PARALLEL").unwrap().parse()?;
+    if limit == 0 {
+        return Err(BenchError::Message(
+            "the parallelism should be at least 1".to_owned()));
+    }
+    Ok(limit)
+}
+
+/// Runs the task for each machine with at most `limit` machines at the
+/// same time.
+///
+/// Returns: the outcomes in the order of the given ips
+pub fn for_each_host<F>(ips: &[String], limit: usize, task: F) -> Vec<HostOutcome>
+        where F: Fn(&str) -> Result<()> + Sync {
+    let next = AtomicUsize::new(0);
+    let outcomes: Mutex<Vec<Option<HostOutcome>>> =
+        Mutex::new(ips.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0 .. limit.min(ips.len()) {
+            scope.spawn(|| loop {
+                let idx = next.fetch_add(1, Ordering::SeqCst);
+                if idx >= ips.len() {
+                    break;
+                }
+
+                let start = Instant::now();
+                let error = task(&ips[idx]).err().map(|e| {
+                    error!("Failed on '{}': {}", ips[idx], e);
+                    e.to_string()
+                });
+                outcomes.lock().unwrap()[idx] = Some(HostOutcome {
+                    ip: ips[idx].clone(),
+                    elapsed: start.elapsed(),
+                    error
+                });
+            });
+        }
+    });
+
+    outcomes.into_inner().unwrap().into_iter().map(Option::unwrap).collect()
+}
+
+/// Prints the output of a machine with each line prefixed by its ip.
+/// The lines of different machines do not interleave.
+pub fn print_with_host(ip: &str, output: &str) {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    for line in output.lines() {
+        let _ = writeln!(stdout, "[{}] {}", ip, line);
+    }
+}
+
+pub fn print_summary(outcomes: &[HostOutcome]) {
+    let rows: Vec<Vec<ColoredString>> = outcomes.iter().map(|outcome| {
+        let (status, error) = match &outcome.error {
+            None => ("ok".green(), ""),
+            // The whole message has been logged
+            Some(e) => ("failed".red(), e.lines().next().unwrap_or(""))
+        };
+        vec![outcome.ip.normal(), status,
+            format!("{:.1}s", outcome.elapsed.as_secs_f64()).normal(), error.normal()]
+    }).collect();
+    print_table(&["HOST", "STATUS", "TIME", "ERROR"], &rows);
+}
+
+/// Prints the rows in columns under the header.
+pub fn print_table(header: &[&str], rows: &[Vec<ColoredString>]) {
+    let header: Vec<ColoredString> = header.iter().map(|h| h.normal()).collect();
+    // A colored string derefs to its text without the escape codes
+    let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    for row in std::iter::once(&header).chain(rows) {
+        let mut line = String::new();
+        for (i, (cell, width)) in row.iter().zip(&widths).enumerate() {
+            if i + 1 < row.len() {
+                line.push_str(&format!("{}{}  ", cell, " ".repeat(width - cell.len())));
+            } else {
+                line.push_str(&cell.to_string());
+            }
+        }
+        println!("{}", line);
+    }
+}
+
+/// Fails if the task failed on any machine.
+pub fn check(outcomes: &[HostOutcome]) -> Result<()> {
+    let failed = outcomes.iter().filter(|o| o.error.is_some()).count();
+    if failed > 0 {
+        return Err(BenchError::Message(format!("failed on {} of {} machines",
+            failed, outcomes.len())));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_each_host() {
+        let ips: Vec<String> = (1 ..= 5).map(|i| format!("10.0.0.{}", i)).collect();
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+
+        let outcomes = for_each_host(&ips, 2, |ip| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(50));
+            running.fetch_sub(1, Ordering::SeqCst);
+
+            if ip == "10.0.0.3" {
+                Err(BenchError::Message("boom".to_owned()))
+            } else {
+                Ok(())
+            }
+        });
+
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+        let ips_in_order: Vec<&str> = outcomes.iter().map(|o| o.ip.as_str()).collect();
+        assert_eq!(ips_in_order, ["10.0.0.1", "10.0.0.2", "10.0.0.3",
+            "10.0.0.4", "10.0.0.5"]);
+        assert_eq!(outcomes[2].error.as_deref(), Some("boom"));
+        assert!(check(&outcomes).is_err());
+        assert!(check(&outcomes[.. 2]).is_ok());
+    }
+}
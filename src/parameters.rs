@@ -1,7 +1,9 @@
 
 use std::path::Path;
+use std::collections::HashMap;
 
 use toml::Value as TomlValue;
+use sha2::{Sha256, Digest};
 
 use crate::error::{Result, BenchError};
 use crate::properties::PropertiesFileMap;
@@ -57,6 +59,17 @@ impl<'a> Parameter<'a> {
         )))
     }
 
+    // Output: (filename, property, value)
+    pub fn get_params(&self) -> Vec<(&'a str, &'a str, &'a str)> {
+        let mut params = Vec::new();
+        for (param_file, param_lines) in &self.params {
+            for (prop, value) in param_lines {
+                params.push((*param_file, *prop, *value));
+            }
+        }
+        params
+    }
+
     pub fn override_properties(&self, files: &mut PropertiesFileMap) {
         for (param_file, param_lines) in &self.params {
             if *param_file == "auto_bencher" {
@@ -79,6 +92,17 @@ impl<'a> Parameter<'a> {
         properties
     }
     
+    // A stable digest of this combination for recognizing the same job
+    pub fn hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        for (param_file, param_lines) in &self.params {
+            for (prop, value) in param_lines {
+                hasher.update(format!("{}\t{}\t{}\n", param_file, prop, value));
+            }
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
     pub fn get_properties_values(&self) -> Vec<&'a str> {
         let mut values = Vec::new();
         for (_, param_lines) in &self.params {
@@ -90,10 +114,33 @@ impl<'a> Parameter<'a> {
     }
 }
 
+// The table in a parameter file that describes how to combine the values
+const COMBINATIONS_TABLE: &str = "combinations";
+const AUTO_BENCHER_TABLE: &str = "auto_bencher";
+// The parameters that the auto-bencher accepts
+const AUTO_BENCHER_PARAMS: &[&str] = &[
+    "jar_dir", "server_count", "server_client_ratio",
+    "max_server_per_machine", "max_client_per_machine", "repetitions", "topology"
+];
+
+// (file id, line id)
+type PropertyId = (usize, usize);
+// (property, value list)
+type ParamLines = Vec<(String, Vec<String>)>;
+// (file id, line id, value)
+type ExcludeRule = Vec<(usize, usize, String)>;
+
 #[derive(Debug)]
 pub struct ParameterList {
     // (filename, (property, value list))
-    param_lists: Vec<(String, Vec<(String, String)>)>
+    param_lists: Vec<(String, ParamLines)>,
+    // Each axis lists the (file id, line id) of the properties whose
+    // values advance together. The combinations are the Cartesian
+    // product of the axes.
+    axes: Vec<Vec<PropertyId>>,
+    // A combination is dropped if it matches all the
+    // (file id, line id, value) of one of the rules
+    exclusions: Vec<ExcludeRule>
 }
 
 impl ParameterList {
@@ -104,51 +151,716 @@ impl ParameterList {
 
         // Read each parameter
         let mut param_lists = Vec::new();
+        let mut derivations = Vec::new();
+        let mut combinations = None;
         if let TomlValue::Table(files) = parameter_list {
             for (filename, toml_table) in files {
-                if let TomlValue::Table(map) = toml_table {
-                    let params = map.into_iter()
-                        .map(|(k, v)| (k, v.as_str().unwrap().to_owned()))
-                        .collect();
+                if filename == COMBINATIONS_TABLE {
+                    combinations = Some(toml_table);
+                } else if let TomlValue::Table(map) = toml_table {
+                    let mut params = Vec::new();
+                    for (key, value) in map {
+                        let result = match value.get("derive") {
+                            // The values will be filled after reading all the lists
+                            Some(_) => Derivation::from_toml(&value).map(|d| {
+                                derivations.push(((param_lists.len(), params.len()), d));
+                                Vec::new()
+                            }),
+                            None => to_value_list(value)
+                        };
+                        let values = result.map_err(|e|
+                            BenchError::Message(format!(
+                                "invalid value of '{}' in table [{}] of '{}': {}",
+                                key, filename, file_path.display(), e)))?;
+                        params.push((key, values));
+                    }
                     param_lists.push((filename.clone(), params));
+                } else {
+                    return Err(BenchError::Message(format!(
+                        "'{}' in '{}' should be a table", filename,
+                        file_path.display())));
                 }
             }
         }
 
-        Ok(ParameterList {
-            param_lists
-        })
+        let mut list = ParameterList {
+            param_lists,
+            axes: Vec::new(),
+            exclusions: Vec::new()
+        };
+        let (mut zipped_groups, exclusions) = match combinations {
+            Some(table) => list.parse_combinations(table).map_err(|e|
+                BenchError::Message(format!("{} (in '{}')", e, file_path.display())))?,
+            None => (Vec::new(), Vec::new())
+        };
+        list.apply_derivations(derivations, &mut zipped_groups)
+            .and_then(|_| list.build_axes(zipped_groups))
+            .map_err(|e| BenchError::Message(
+                format!("{} (in '{}')", e, file_path.display())))?;
+        list.exclusions = exclusions;
+
+        // The exclusion rules may remove all the combinations
+        if list.to_vec().is_empty() {
+            return Err(BenchError::Message(format!(
+                "the parameter file produces no job (in '{}')", file_path.display())));
+        }
+
+        Ok(list)
     }
 
-    pub fn to_vec(&self) -> Vec<Parameter> {
+    /// Checks if every property in the parameter file exists in the
+    /// given properties files, so that a typo does not silently leave
+    /// a property unchanged.
+    pub fn validate(&self, files: &PropertiesFileMap) -> Result<()> {
+        let mut unknowns = Vec::new();
+
+        for (filename, param_lines) in &self.param_lists {
+            let known = if filename == AUTO_BENCHER_TABLE {
+                AUTO_BENCHER_PARAMS.to_vec()
+            } else {
+                match files.get_properties(filename) {
+                    Some(properties) => properties,
+                    None => {
+                        unknowns.push(format!("[{}]{}", filename,
+                            suggest(filename, &files.get_filenames())));
+                        continue;
+                    }
+                }
+            };
+
+            for (prop, _) in param_lines {
+                if !known.contains(&prop.as_str()) {
+                    unknowns.push(format!("{}.{}{}", filename, prop,
+                        suggest(prop, &known)));
+                }
+            }
+        }
+
+        if unknowns.is_empty() {
+            Ok(())
+        } else {
+            Err(BenchError::Message(format!(
+                "unknown properties in the parameter file:\n  {}",
+                unknowns.join("\n  "))))
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<Parameter<'_>> {
         let mut result = Vec::new();
-        self.iterate_parameters(0, 0, Parameter::empty(), &mut result);
+        let mut choices = Vec::with_capacity(self.axes.len());
+        self.iterate_parameters(&mut choices, &mut result);
         result
     }
 
-    // Find all combinations of parameters. Implemented by recursion
-    fn iterate_parameters<'a>(&'a self, file_id: usize, line_id: usize,
-            current: Parameter<'a>, results: &mut Vec<Parameter<'a>>) {
-        // Check if the file id exceeds
-        if file_id < self.param_lists.len() {
-            let (filename, param_lines) = &self.param_lists[file_id];
-
-            // Check if the line id exceeds
-            if line_id < param_lines.len() {
-                // Read a line and split it
-                let (prop, value_list) = &param_lines[line_id];
-                for value in value_list.split(" ") {
-                    let mut new = current.clone();
-                    new.add_param(filename.as_str(), prop.as_str(), value);
-                    self.iterate_parameters(file_id, line_id + 1, new, results);
+    // Format:
+    //
+    // [combinations]
+    // zip = [["file.property", "file.property", ...], ...]
+    // exclude = [{ "file.property" = "value", ... }, ...]
+    fn parse_combinations(&self, table: TomlValue)
+            -> Result<(Vec<Vec<PropertyId>>, Vec<ExcludeRule>)> {
+        let table = match table {
+            TomlValue::Table(table) => table,
+            _ => return Err(BenchError::Message(format!(
+                "'{}' should be a table", COMBINATIONS_TABLE)))
+        };
+
+        let mut zipped_groups = Vec::new();
+        let mut exclusions = Vec::new();
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("zip", TomlValue::Array(groups)) => {
+                    for group in groups {
+                        let keys = group.as_array().ok_or_else(|| BenchError::Message(
+                            "each group in 'zip' should be an array of properties".to_owned()))?;
+                        let mut members = Vec::new();
+                        for key in keys {
+                            let key = key.as_str().ok_or_else(|| BenchError::Message(
+                                format!("'{}' in 'zip' is not a string", key)))?;
+                            members.push(self.find_property(key)?);
+                        }
+                        if members.is_empty() {
+                            return Err(BenchError::Message(
+                                "a group in 'zip' is empty".to_owned()));
+                        }
+                        zipped_groups.push(members);
+                    }
+                },
+                ("exclude", TomlValue::Array(rules)) => {
+                    for rule in rules {
+                        let rule = match rule {
+                            TomlValue::Table(rule) => rule,
+                            _ => return Err(BenchError::Message(
+                                "each rule in 'exclude' should be a table".to_owned()))
+                        };
+                        let mut conditions = Vec::new();
+                        for (key, value) in rule {
+                            let (file_id, line_id) = self.find_property(&key)?;
+                            let value = scalar_to_string(&value).ok_or_else(|| BenchError::Message(
+                                format!("the value of '{}' in 'exclude' should be a single value", key)))?;
+                            conditions.push((file_id, line_id, value));
+                        }
+                        exclusions.push(conditions);
+                    }
+                },
+                (key, _) => return Err(BenchError::Message(format!(
+                    "unknown or invalid setting '{}' in '{}'", key, COMBINATIONS_TABLE)))
+            }
+        }
+
+        Ok((zipped_groups, exclusions))
+    }
+
+    // Input: "[filename].[property]"
+    fn find_property(&self, key: &str) -> Result<PropertyId> {
+        let mut split = key.splitn(2, '.');
+        let filename = split.next().unwrap();
+        let property = split.next().unwrap_or("");
+
+        for (file_id, (param_file, param_lines)) in self.param_lists.iter().enumerate() {
+            if param_file != filename {
+                continue;
+            }
+            for (line_id, (prop, _)) in param_lines.iter().enumerate() {
+                if prop == property {
+                    return Ok((file_id, line_id));
                 }
-            } else {
-                // To next file
-                self.iterate_parameters(file_id + 1, 0, current, results);
             }
-        } else {
+        }
+
+        Err(BenchError::Message(format!(
+            "cannot find property '{}' in the parameter file", key)))
+    }
+
+    // A derived property has a value for each value of its source
+    // and advances together with the source
+    fn apply_derivations(&mut self, derivations: Vec<(PropertyId, Derivation)>,
+            zipped_groups: &mut Vec<Vec<PropertyId>>) -> Result<()> {
+        let derived_ids: Vec<PropertyId> = derivations.iter()
+            .map(|(id, _)| *id).collect();
+
+        for (id, derivation) in derivations {
+            let source_id = self.find_property(&derivation.source)?;
+            if derived_ids.contains(&source_id) {
+                return Err(BenchError::Message(format!(
+                    "'{}' cannot be derived from another derived property '{}'",
+                    self.property_key(id.0, id.1), derivation.source)));
+            }
+            if zipped_groups.iter().any(|group| group.contains(&id)) {
+                return Err(BenchError::Message(format!(
+                    "derived property '{}' cannot be zipped",
+                    self.property_key(id.0, id.1))));
+            }
+
+            let mut values = Vec::new();
+            for value in &self.param_lists[source_id.0].1[source_id.1].1 {
+                values.push(derivation.apply(value).map_err(|e|
+                    BenchError::Message(format!(
+                        "cannot derive '{}' from '{}': {}",
+                        self.property_key(id.0, id.1), derivation.source, e)))?);
+            }
+            self.param_lists[id.0].1[id.1].1 = values;
+
+            match zipped_groups.iter_mut().find(|group| group.contains(&source_id)) {
+                Some(group) => group.push(id),
+                None => zipped_groups.push(vec![source_id, id])
+            }
+        }
+
+        Ok(())
+    }
+
+    fn build_axes(&mut self, zipped_groups: Vec<Vec<PropertyId>>) -> Result<()> {
+        // Check the groups
+        let mut group_of: HashMap<PropertyId, usize> = HashMap::new();
+        for (group_id, group) in zipped_groups.iter().enumerate() {
+            let (first_file, first_line) = group[0];
+            let count = self.param_lists[first_file].1[first_line].1.len();
+            for &(file_id, line_id) in group {
+                if group_of.insert((file_id, line_id), group_id).is_some() {
+                    return Err(BenchError::Message(format!(
+                        "'{}' is zipped more than once",
+                        self.property_key(file_id, line_id))));
+                }
+                if self.param_lists[file_id].1[line_id].1.len() != count {
+                    return Err(BenchError::Message(format!(
+                        "zipped properties '{}' and '{}' have different numbers of values",
+                        self.property_key(first_file, first_line),
+                        self.property_key(file_id, line_id))));
+                }
+            }
+        }
+
+        // Each group becomes an axis at the position of its first property
+        let mut added_groups = Vec::new();
+        for (file_id, (_, param_lines)) in self.param_lists.iter().enumerate() {
+            for line_id in 0 .. param_lines.len() {
+                match group_of.get(&(file_id, line_id)) {
+                    Some(group_id) => {
+                        if !added_groups.contains(group_id) {
+                            added_groups.push(*group_id);
+                            self.axes.push(zipped_groups[*group_id].clone());
+                        }
+                    },
+                    None => self.axes.push(vec![(file_id, line_id)])
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn property_key(&self, file_id: usize, line_id: usize) -> String {
+        let (filename, param_lines) = &self.param_lists[file_id];
+        format!("{}.{}", filename, param_lines[line_id].0)
+    }
+
+    // Find all combinations of parameters. Implemented by recursion.
+    // `choices` records the chosen value of each axis.
+    fn iterate_parameters<'a>(&'a self, choices: &mut Vec<usize>,
+            results: &mut Vec<Parameter<'a>>) {
+        // Check if the axis id exceeds
+        if choices.len() < self.axes.len() {
+            let (file_id, line_id) = self.axes[choices.len()][0];
+            let value_count = self.param_lists[file_id].1[line_id].1.len();
+            for value_id in 0 .. value_count {
+                choices.push(value_id);
+                self.iterate_parameters(choices, results);
+                choices.pop();
+            }
+        } else if !self.is_excluded(choices) {
             // Reach the bottom, save the result
-            results.push(current);
+            let mut parameter = Parameter::empty();
+            for (file_id, (filename, param_lines)) in self.param_lists.iter().enumerate() {
+                for (line_id, (prop, values)) in param_lines.iter().enumerate() {
+                    let value_id = self.chosen_value(choices, file_id, line_id);
+                    parameter.add_param(filename, prop, &values[value_id]);
+                }
+            }
+            results.push(parameter);
         }
     }
-}
\ No newline at end of file
+
+    fn chosen_value(&self, choices: &[usize], file_id: usize, line_id: usize) -> usize {
+        let axis_id = self.axes.iter()
+            .position(|axis| axis.contains(&(file_id, line_id)))
+            .unwrap();
+        choices[axis_id]
+    }
+
+    fn is_excluded(&self, choices: &[usize]) -> bool {
+        self.exclusions.iter().any(|rule| {
+            rule.iter().all(|(file_id, line_id, value)| {
+                let value_id = self.chosen_value(choices, *file_id, *line_id);
+                self.param_lists[*file_id].1[*line_id].1[value_id] == *value
+            })
+        })
+    }
+}
+
+// Output: " (did you mean '...'?)" or "" if nothing is close enough
+fn suggest(name: &str, candidates: &[&str]) -> String {
+    candidates.iter()
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|(distance, _)| *distance <= name.len() / 2)
+        .min()
+        .map(|(_, c)| format!(" (did you mean '{}'?)", c))
+        .unwrap_or_default()
+}
+
+// Levenshtein distance
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0 ..= b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + if ca == *cb { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+// A string is a list of values separated by spaces. An array lists
+// the values, in which each string is kept as a single value.
+// A table generates the values (see `generate_values`).
+fn to_value_list(value: TomlValue) -> std::result::Result<Vec<String>, String> {
+    let values = match value {
+        TomlValue::String(s) => s.split(' ').map(|s| s.to_owned()).collect(),
+        TomlValue::Table(table) => generate_values(&table)?,
+        TomlValue::Array(array) => {
+            let mut values = Vec::new();
+            for element in &array {
+                values.push(scalar_to_string(element).ok_or_else(||
+                    format!("'{}' in the array is not a string, a number or a boolean",
+                        element))?);
+            }
+            values
+        },
+        other => vec![scalar_to_string(&other).ok_or_else(||
+            format!("'{}' is not a string, a number, a boolean, an array or a generator",
+                other))?]
+    };
+
+    if values.is_empty() {
+        return Err("no value is given".to_owned());
+    }
+    Ok(values)
+}
+
+fn scalar_to_string(value: &TomlValue) -> Option<String> {
+    match value {
+        TomlValue::String(s) => Some(s.clone()),
+        TomlValue::Integer(i) => Some(i.to_string()),
+        // Debug format keeps the decimal point (e.g. "1.0")
+        TomlValue::Float(f) => Some(format!("{:?}", f)),
+        TomlValue::Boolean(b) => Some(b.to_string()),
+        _ => None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Number {
+    Integer(i64),
+    Float(f64)
+}
+
+impl Number {
+    fn from_toml(value: &TomlValue) -> Option<Number> {
+        match value {
+            TomlValue::Integer(i) => Some(Number::Integer(*i)),
+            TomlValue::Float(f) => Some(Number::Float(*f)),
+            _ => None
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Integer(i) => i as f64,
+            Number::Float(f) => f
+        }
+    }
+
+    fn as_string(self) -> String {
+        match self {
+            Number::Integer(i) => i.to_string(),
+            Number::Float(f) => format!("{:?}", f)
+        }
+    }
+}
+
+// A generator giving more values is most likely a typo
+const MAX_GENERATED_VALUES: usize = 10000;
+
+// Formats:
+//
+// { range = [start, end, step] }: start, start + step, ... until end (inclusive).
+//   The step is 1 by default.
+// { geometric = [start, end, ratio] }: start, start * ratio, ... until end
+//   (inclusive). The ratio is 2 by default, i.e. powers of two.
+fn generate_values(table: &toml::value::Table)
+        -> std::result::Result<Vec<String>, String> {
+    let (kind, args) = match table.iter().next() {
+        Some(pair) if table.len() == 1 => pair,
+        _ => return Err("a generator should have exactly one setting".to_owned())
+    };
+    let args: Vec<Number> = args.as_array()
+        .and_then(|array| array.iter().map(Number::from_toml).collect())
+        .ok_or_else(|| format!("the arguments of '{}' should be an array of numbers", kind))?;
+    if args.len() < 2 || args.len() > 3 {
+        return Err(format!("'{}' takes 2 or 3 arguments", kind));
+    }
+    let (start, end) = (args[0], args[1]);
+
+    let values = match kind.as_str() {
+        "range" => {
+            let step = args.get(2).cloned().unwrap_or(Number::Integer(1));
+            if step.as_f64() == 0.0
+                    || (end.as_f64() - start.as_f64()) * step.as_f64() < 0.0 {
+                return Err(format!("range from {} to {} never ends with step {}",
+                    start.as_string(), end.as_string(), step.as_string()));
+            }
+            let count = ((end.as_f64() - start.as_f64()) / step.as_f64()
+                + 1e-9).floor() + 1.0;
+            if count.is_nan() || count > MAX_GENERATED_VALUES as f64 {
+                return Err(format!("range from {} to {} with step {} gives more than {} values",
+                    start.as_string(), end.as_string(), step.as_string(),
+                    MAX_GENERATED_VALUES));
+            }
+            let count = count as i64;
+            match (start, step) {
+                (Number::Integer(start), Number::Integer(step)) =>
+                    (0 .. count).map(|i| i.checked_mul(step)
+                        .and_then(|offset| start.checked_add(offset))
+                        .map(Number::Integer)
+                        .ok_or_else(|| format!("range from {} with step {} overflows",
+                            start, step)))
+                        .collect::<std::result::Result<_, _>>()?,
+                _ => (0 .. count).map(|i| Number::Float(
+                    start.as_f64() + i as f64 * step.as_f64())).collect()
+            }
+        },
+        "geometric" => {
+            let ratio = args.get(2).cloned().unwrap_or(Number::Integer(2));
+            if start.as_f64() <= 0.0 || ratio.as_f64() <= 1.0 {
+                return Err("geometric series needs a positive start and a ratio larger than 1"
+                    .to_owned());
+            }
+            let mut values = Vec::new();
+            let mut value = start;
+            while value.as_f64() <= end.as_f64() * (1.0 + 1e-9) {
+                if values.len() == MAX_GENERATED_VALUES {
+                    return Err(format!("geometric series from {} to {} gives more than {} values",
+                        start.as_string(), end.as_string(), MAX_GENERATED_VALUES));
+                }
+                values.push(value);
+                value = match (value, ratio) {
+                    (Number::Integer(v), Number::Integer(r)) => Number::Integer(
+                        v.checked_mul(r).ok_or_else(|| format!(
+                            "geometric series from {} with ratio {} overflows", start.as_string(), r))?),
+                    (v, r) => Number::Float(v.as_f64() * r.as_f64())
+                };
+            }
+            values
+        },
+        other => return Err(format!("unknown generator '{}'", other))
+    };
+
+    Ok(values.into_iter().map(Number::as_string).collect())
+}
+
+// Format: { derive = "file.property", multiply = 1, add = 0 }
+// The value is computed as: [source value] * multiply + add
+#[derive(Debug)]
+struct Derivation {
+    source: String,
+    multiply: Number,
+    add: Number
+}
+
+impl Derivation {
+    fn from_toml(value: &TomlValue) -> std::result::Result<Derivation, String> {
+        let mut derivation = Derivation {
+            source: String::new(),
+            multiply: Number::Integer(1),
+            add: Number::Integer(0)
+        };
+
+        for (key, value) in value.as_table().unwrap() {
+            match key.as_str() {
+                "derive" => derivation.source = value.as_str()
+                    .ok_or("'derive' should be a property name")?.to_owned(),
+                "multiply" => derivation.multiply = Number::from_toml(value)
+                    .ok_or("'multiply' should be a number")?,
+                "add" => derivation.add = Number::from_toml(value)
+                    .ok_or("'add' should be a number")?,
+                other => return Err(format!("unknown setting '{}' of a derived value", other))
+            }
+        }
+
+        Ok(derivation)
+    }
+
+    fn apply(&self, value: &str) -> std::result::Result<String, String> {
+        let result = match (self.multiply, self.add, value.parse::<i64>()) {
+            (Number::Integer(multiply), Number::Integer(add), Ok(value)) =>
+                Number::Integer(value.checked_mul(multiply)
+                    .and_then(|v| v.checked_add(add))
+                    .ok_or_else(|| format!("{} * {} + {} overflows", value, multiply, add))?),
+            _ => Number::Float(value.parse::<f64>()
+                .map_err(|_| format!("'{}' is not a number", value))?
+                * self.multiply.as_f64() + self.add.as_f64())
+        };
+        Ok(result.as_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::parse_parameters as parse;
+
+    fn values(list: &ParameterList) -> Vec<Vec<&str>> {
+        list.to_vec().iter().map(|p| p.get_properties_values()).collect()
+    }
+
+    #[test]
+    fn test_cartesian_product() {
+        let list = parse(r#"
+            [auto_bencher]
+            server_count = "2 4"
+            [vanillabench]
+            NUM_RTES = "10 20"
+        "#).unwrap();
+        assert_eq!(values(&list), vec![
+            vec!["2", "10"], vec!["2", "20"], vec!["4", "10"], vec!["4", "20"]
+        ]);
+    }
+
+    #[test]
+    fn test_zip_and_exclude() {
+        let list = parse(r#"
+            [auto_bencher]
+            server_count = "2 4 8"
+            [vanillabench]
+            NUM_RTES = "10 20"
+            NUM_WAREHOUSES = "20 40 80"
+
+            [combinations]
+            zip = [["auto_bencher.server_count", "vanillabench.NUM_WAREHOUSES"]]
+            exclude = [{ "auto_bencher.server_count" = "8", "vanillabench.NUM_RTES" = "10" }]
+        "#).unwrap();
+        assert_eq!(values(&list), vec![
+            vec!["2", "10", "20"], vec!["2", "20", "20"],
+            vec!["4", "10", "40"], vec!["4", "20", "40"],
+            vec!["8", "20", "80"]
+        ]);
+
+        let err = parse(r#"
+            [auto_bencher]
+            server_count = "2 4"
+            [combinations]
+            exclude = [{ "auto_bencher.server_count" = "2" }, { "auto_bencher.server_count" = "4" }]
+        "#).unwrap_err();
+        assert!(err.to_string().contains("the parameter file produces no job"));
+    }
+
+    #[test]
+    fn test_typed_values() {
+        let list = parse(r#"
+            [auto_bencher]
+            server_count = [2, 4]
+            server_client_ratio = 1.0
+            [vanilladb]
+            USE_O_DIRECT = true
+            [vanillacomm]
+            SERVER_VIEW = ["0 127.0.0.1 30000, 1 127.0.0.1 30001"]
+        "#).unwrap();
+        assert_eq!(values(&list), vec![
+            vec!["1.0", "2", "0 127.0.0.1 30000, 1 127.0.0.1 30001", "true"],
+            vec!["1.0", "4", "0 127.0.0.1 30000, 1 127.0.0.1 30001", "true"]
+        ]);
+
+        let err = parse(r#"
+            [vanillabench]
+            NUM_RTES = [[1, 2]]
+        "#).unwrap_err();
+        assert!(err.to_string().contains("'NUM_RTES' in table [vanillabench]"));
+        assert!(parse("[vanillabench]\nNUM_RTES = []").is_err());
+        assert!(parse("server_count = 1").is_err());
+    }
+
+    #[test]
+    fn test_generators() {
+        let list = parse(r#"
+            [auto_bencher]
+            server_count = { geometric = [1, 8] }
+            [vanillabench]
+            NUM_RTES = { range = [10, 30, 10] }
+            NUM_WAREHOUSES = { derive = "auto_bencher.server_count", multiply = 10 }
+            [elasql]
+            RATIO = { range = [0.5, 1.0, 0.25] }
+            [combinations]
+            exclude = [{ "vanillabench.NUM_WAREHOUSES" = "80" }]
+        "#).unwrap();
+        let values = values(&list);
+        assert_eq!(values.len(), 3 * 3 * 3);
+        assert_eq!(values[0], vec!["1", "0.5", "10", "10"]);
+        assert_eq!(values[1], vec!["1", "0.5", "20", "10"]);
+        assert_eq!(values[2], vec!["1", "0.5", "30", "10"]);
+        assert_eq!(values[3], vec!["1", "0.75", "10", "10"]);
+        assert_eq!(values[9], vec!["2", "0.5", "10", "20"]);
+        assert_eq!(values[26], vec!["4", "1.0", "30", "40"]);
+
+        assert!(parse(r#"
+            [vanillabench]
+            NUM_RTES = { range = [10, 1, 10] }
+        "#).is_err());
+        assert!(parse(r#"
+            [vanillabench]
+            NUM_RTES = { derive = "vanillabench.NO_SUCH_PROPERTY" }
+        "#).is_err());
+    }
+
+    #[test]
+    fn test_unbounded_generators() {
+        assert!(parse(r#"
+            [vanillabench]
+            NUM_RTES = { range = [0, 1e12] }
+        "#).is_err());
+        assert!(parse(r#"
+            [vanillabench]
+            NUM_RTES = { range = [0, 9223372036854775807, 4611686018427387904] }
+        "#).is_err());
+        assert!(parse(r#"
+            [vanillabench]
+            NUM_RTES = { geometric = [1, 9223372036854775807] }
+        "#).is_err());
+        assert!(parse(r#"
+            [vanillabench]
+            NUM_RTES = { geometric = [1.0, inf, 1.5] }
+        "#).is_err());
+        assert!(parse(r#"
+            [vanillabench]
+            NUM_RTES = 4611686018427387904
+            NUM_WAREHOUSES = { derive = "vanillabench.NUM_RTES", multiply = 4 }
+        "#).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let files = PropertiesFileMap::from_dir(Path::new("properties")).unwrap();
+        let list = parse(r#"
+            [auto_bencher]
+            server_count = "3"
+            [vanillabench]
+            "org.vanilladb.bench.BenchmarkerParameters.NUM_RTES" = "10"
+        "#).unwrap();
+        assert!(list.validate(&files).is_ok());
+
+        let list = parse(r#"
+            [auto_bencher]
+            server_cnt = "3"
+            [vanillabench]
+            "org.vanilladb.bench.BenchmarkerParameters.NUM_RTE" = "10"
+            [vanilladbb]
+            "org.vanilladb.core.storage.buffer.BufferMgr.BUFFER_POOL_SIZE" = "1024"
+        "#).unwrap();
+        let message = list.validate(&files).unwrap_err().to_string();
+        assert!(message.contains(
+            "auto_bencher.server_cnt (did you mean 'server_count'?)"));
+        assert!(message.contains(
+            "(did you mean 'org.vanilladb.bench.BenchmarkerParameters.NUM_RTES'?)"));
+        assert!(message.contains("[vanilladbb] (did you mean 'vanilladb'?)"));
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("abc", "abc"), 0);
+    }
+
+    #[test]
+    fn test_invalid_combinations() {
+        assert!(parse(r#"
+            [auto_bencher]
+            server_count = "2 4 8"
+            [vanillabench]
+            NUM_WAREHOUSES = "20 40"
+            [combinations]
+            zip = [["auto_bencher.server_count", "vanillabench.NUM_WAREHOUSES"]]
+        "#).is_err());
+        assert!(parse(r#"
+            [auto_bencher]
+            server_count = "2 4 8"
+            [combinations]
+            exclude = [{ "auto_bencher.no_such_key" = "8" }]
+        "#).is_err());
+    }
+}
@@ -1,38 +1,42 @@
 
 use std::fs;
+use std::thread;
 use std::path::{Path, PathBuf};
 
 use log::*;
+use sha2::{Sha256, Digest};
 
-use crate::error::Result;
+use crate::error::{Result, BenchError};
 use crate::parameters::Parameter;
 use crate::properties::PropertiesFileMap;
-use crate::command;
 use crate::config::Config;
 use crate::connections::ConnectionInfo;
+use crate::transport::Transport;
+use crate::jar_cache::{self, CachedJar};
 
-const BENCH_DIR: &'static str = "benchmarker";
-const PROP_DIR: &'static str = "props";
+const BENCH_DIR: &str = "benchmarker";
+const PROP_DIR: &str = "props";
+const CHECKSUM_FILENAME: &str = ".checksum";
 
 // Output: vm args for properties files
-pub fn prepare_bench_dir(config: &Config, parameter: &Parameter,
+pub fn prepare_bench_dir(parameter: &Parameter,
         sequencer: &Option<ConnectionInfo>, server_list: &Vec<ConnectionInfo>,
         client_list: &Vec<ConnectionInfo>) -> Result<String> {
     info!("Preparing the benchmarker directory...");
 
-    // Ensure the existance of the benchmarker dir
+    // Start from an empty benchmarker dir. The jars are not in it
+    // but in the jar cache of each machine.
+    if Path::new(BENCH_DIR).exists() {
+        fs::remove_dir_all(BENCH_DIR)?;
+    }
     fs::create_dir_all(BENCH_DIR)?;
 
-    // Copy the jar files to the benchmark dir
-    let dirname = parameter.get_autobencher_param("jar_dir")?;
-    copy_jars(dirname)?;
-
     // Read the default properties
-    let mut map = PropertiesFileMap::from_dir(&Path::new("properties"))?;
+    let mut map = PropertiesFileMap::from_dir(Path::new("properties"))?;
 
     // Apply the parameters
     parameter.override_properties(&mut map);
-    set_paths(config, &mut map);
+    set_paths(&mut map);
     set_connection_properties(&mut map, sequencer, server_list, client_list)?;
     set_elasql_properties(&mut map, server_list.len());
 
@@ -40,35 +44,22 @@ pub fn prepare_bench_dir(config: &Config, parameter: &Parameter,
     let prop_dir_path: PathBuf = [BENCH_DIR, PROP_DIR].iter().collect();
     map.output_to_dir(&prop_dir_path)?;
 
-    let mut remote_prop_dir_path = PathBuf::new();
-    remote_prop_dir_path.push(&config.system.remote_work_dir);
-    remote_prop_dir_path.push(prop_dir_path);
-    map.get_vm_args(&remote_prop_dir_path)
+    // The processes run in the working directories of their machines
+    map.get_vm_args(&prop_dir_path)
 }
 
-fn copy_jars(dir_name: &str) -> Result<()> {
-    let dir_path = format!("jars/{}", dir_name);
-    let filenames = vec!["server.jar", "client.jar"];
-    for filename in filenames {
-        let jar_path = format!("{}/{}", dir_path, filename);
-        // Check if the jar exists
-        command::ls(&jar_path)?;
-        // Copy it to the benchmarker directory
-        command::cp(false, &jar_path, BENCH_DIR)?;
-    }
-    Ok(())
-}
-
-fn set_paths(config: &Config, map: &mut PropertiesFileMap) {
+// The paths are relative to the working directory, which may differ
+// between the machines
+fn set_paths(map: &mut PropertiesFileMap) {
     map.set(
         "vanilladb",
         "org.vanilladb.core.storage.file.FileMgr.DB_FILES_DIR",
-        &format!("{}/databases", config.system.remote_work_dir)
+        "databases"
     );
     map.set(
         "vanillabench",
         "org.vanilladb.bench.StatisticMgr.OUTPUT_DIR",
-        &format!("{}/results", config.system.remote_work_dir)
+        "results"
     );
 }
 
@@ -118,4 +109,224 @@ fn set_elasql_properties(map: &mut PropertiesFileMap, server_count: usize) {
         "org.elasql.storage.metadata.PartitionMetaMgr.NUM_PARTITIONS",
         &server_count.to_string()
     );
-}
\ No newline at end of file
+}
+
+// Copies the generated properties files to `[dest]/props`
+pub fn copy_props(dest: &Path) -> Result<()> {
+    let prop_dir: PathBuf = [BENCH_DIR, PROP_DIR].iter().collect();
+    let dest = dest.join(PROP_DIR);
+    fs::create_dir_all(&dest)?;
+    for entry in fs::read_dir(prop_dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            fs::copy(&path, dest.join(path.file_name().unwrap()))?;
+        }
+    }
+    Ok(())
+}
+
+// Sends the benchmarker directory to each machine once. The machines
+// already having the same content are skipped. The jars are sent to
+// the jar caches of the machines and linked into the directory.
+pub fn distribute_bench_dir(config: &Config, transport: &dyn Transport,
+        jar_dir: &str, ips: &[&str]) -> Result<()> {
+    info!("Distributing the benchmarker directory...");
+
+    let jars = jar_cache::read_jars(&Path::new("jars").join(jar_dir))?;
+    let hosts: Vec<(&str, &str)> = ips.iter()
+        .map(|ip| (*ip, config.work_dir(ip)))
+        .collect();
+    let (sent, bytes) = distribute(transport, Path::new(BENCH_DIR), &jars,
+        &hosts)?;
+
+    info!("Sent the benchmarker to {} of {} machines ({} bytes transferred)",
+        sent, ips.len(), bytes);
+    Ok(())
+}
+
+// Sends the directory to each (ip, remote working dir).
+// Output: (the number of machines sent to, the bytes transferred)
+fn distribute(transport: &dyn Transport, bench_dir: &Path, jars: &[CachedJar],
+        hosts: &[(&str, &str)]) -> Result<(usize, u64)> {
+    let (checksum, size) = checksum_dir(bench_dir, jars)?;
+    let local_path = bench_dir.to_str().unwrap();
+    let dir_name = bench_dir.file_name().unwrap().to_str().unwrap();
+
+    // The errors are logged in the threads since they cannot be sent
+    let results: Vec<Option<(bool, u64)>> = thread::scope(|scope| {
+        let handles: Vec<_> = hosts.iter().map(|(ip, remote_work_dir)| {
+            let checksum = &checksum;
+            scope.spawn(move || {
+                let remote_path = format!("{}/{}", remote_work_dir, dir_name);
+                let dir = BenchDir {
+                    local_path,
+                    remote_work_dir,
+                    remote_path: &remote_path,
+                    checksum,
+                    size,
+                    jars
+                };
+                match send_if_changed(transport, ip, &dir) {
+                    Ok(sent) => Some(sent),
+                    Err(e) => {
+                        error!("Failed to send the benchmarker to '{}': {}", ip, e);
+                        None
+                    }
+                }
+            })
+        }).collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    });
+
+    let mut sent = 0;
+    let mut bytes = 0;
+    for result in results {
+        let (dir_sent, sent_bytes) = result.ok_or_else(|| BenchError::Message(
+            "failed to distribute the benchmarker directory".to_owned()))?;
+        if dir_sent {
+            sent += 1;
+        }
+        bytes += sent_bytes;
+    }
+    Ok((sent, bytes))
+}
+
+struct BenchDir<'a> {
+    local_path: &'a str,
+    remote_work_dir: &'a str,
+    remote_path: &'a str,
+    checksum: &'a str,
+    // without the jars
+    size: u64,
+    jars: &'a [CachedJar]
+}
+
+// Output: (whether the directory is sent, the bytes transferred)
+fn send_if_changed(transport: &dyn Transport, ip: &str, dir: &BenchDir)
+        -> Result<(bool, u64)> {
+    let mut bytes = 0;
+    for jar in dir.jars {
+        if jar_cache::ensure_cached(transport, ip, dir.remote_work_dir, jar)? {
+            bytes += jar.size;
+        }
+    }
+
+    let checksum_path = format!("{}/{}", dir.remote_path, CHECKSUM_FILENAME);
+    let remote_checksum = match transport.exec(ip, &format!("cat {}", checksum_path)) {
+        Ok(output) => Some(output),
+        Err(BenchError::CommandFailedOnRemote(_, _, 1, _)) => None,
+        Err(e) => return Err(e)
+    };
+    if remote_checksum.as_deref().map(str::trim) == Some(dir.checksum) {
+        debug!("The benchmarker on '{}' is up to date", ip);
+        return Ok((false, bytes));
+    }
+
+    debug!("Sending the benchmarker to '{}'...", ip);
+    transport.exec(ip, &format!("rm -rf {}", dir.remote_path))?;
+    transport.upload(true, ip, dir.local_path, dir.remote_work_dir)?;
+    for jar in dir.jars {
+        jar_cache::link(transport, ip, dir.remote_path, jar)?;
+    }
+    // Write the checksum at last so that an interrupted upload
+    // will not be taken as complete
+    transport.exec(ip, &format!("echo {} > {}", dir.checksum, checksum_path))?;
+    Ok((true, bytes + dir.size))
+}
+
+// Output: (checksum, total size of the files)
+fn checksum_dir(dir: &Path, jars: &[CachedJar]) -> Result<(String, u64)> {
+    let mut files = Vec::new();
+    list_files(dir, dir, &mut files)?;
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    let mut size = 0;
+    for relative_path in files {
+        let content = fs::read(dir.join(&relative_path))?;
+        size += content.len() as u64;
+        hasher.update(relative_path.to_str().unwrap());
+        hasher.update([0]);
+        hasher.update(&content);
+    }
+    // The linked jars are also a part of the directory
+    for jar in jars {
+        hasher.update(format!("{}\t{}\n", jar.filename, jar.hash));
+    }
+    Ok((format!("{:x}", hasher.finalize()), size))
+}
+
+fn list_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            list_files(root, &path, files)?;
+        } else {
+            files.push(path.strip_prefix(root).unwrap().to_owned());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_path;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_distribute() {
+        let dir = temp_path("dist");
+        let bench_dir = dir.join(BENCH_DIR);
+        let jar_dir = dir.join("jars");
+        fs::create_dir_all(bench_dir.join(PROP_DIR)).unwrap();
+        fs::create_dir_all(&jar_dir).unwrap();
+        fs::write(bench_dir.join(PROP_DIR).join("a.properties"), "a=1").unwrap();
+        fs::write(jar_dir.join("server.jar"), "server").unwrap();
+        fs::write(jar_dir.join("client.jar"), "client").unwrap();
+        let jars = jar_cache::read_jars(&jar_dir).unwrap();
+        let (checksum, size) = checksum_dir(&bench_dir, &jars).unwrap();
+        assert_eq!(size, 3);
+
+        // 10.0.0.1 is up to date, 10.0.0.2 has an older version
+        // and 10.0.0.3 has nothing
+        let answer = checksum.clone();
+        let mock = MockTransport::new(move |ip, cmd| {
+            let not_found = Err(BenchError::CommandFailedOnRemote(ip.to_owned(),
+                cmd.to_owned(), 1, "".to_owned()));
+            match (ip, cmd.split(' ').next().unwrap()) {
+                ("10.0.0.1", "cat") => Ok(format!("{}\n", answer)),
+                ("10.0.0.2", "cat") => Ok("old".to_owned()),
+                ("10.0.0.3", "cat") => not_found,
+                ("10.0.0.1", "test") => Ok("".to_owned()),
+                (_, "test") => not_found,
+                _ => Ok("".to_owned())
+            }
+        });
+
+        let hosts = [("10.0.0.1", "work"), ("10.0.0.2", "work"), ("10.0.0.3", "work")];
+        let (sent, bytes) = distribute(&mock, &bench_dir, &jars, &hosts).unwrap();
+        // 2 * (3 bytes of properties + 12 bytes of jars)
+        assert_eq!((sent, bytes), (2, 30));
+
+        let records = mock.records();
+        let local_path = bench_dir.to_str().unwrap();
+        assert!(!records.iter().any(|r| r.starts_with("upload 10.0.0.1")));
+        for ip in &["10.0.0.2", "10.0.0.3"] {
+            assert!(records.contains(
+                &format!("upload {} {} work", ip, local_path)));
+            assert!(records.contains(&format!(
+                "exec {} ln -sf ../jar-cache/{}.jar work/benchmarker/server.jar",
+                ip, jars[0].hash)));
+            assert!(records.contains(&format!(
+                "exec {} echo {} > work/benchmarker/.checksum", ip, checksum)));
+        }
+
+        // Changing a jar changes the checksum
+        fs::write(jar_dir.join("server.jar"), "server2").unwrap();
+        let jars = jar_cache::read_jars(&jar_dir).unwrap();
+        assert_ne!(checksum_dir(&bench_dir, &jars).unwrap().0, checksum);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
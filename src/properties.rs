@@ -2,9 +2,10 @@
 use std::collections::HashMap;
 use std::path::Path;
 use std::fs::{self, File};
-use std::io::BufReader;
+use std::io::{BufReader, BufWriter};
 
 use serde::Deserialize;
+use java_properties::PropertiesWriter;
 use log::*;
 
 use crate::error::Result;
@@ -29,6 +30,10 @@ impl PropertiesFile {
         })
     }
 
+    fn get(&self, property: &str) -> Option<&str> {
+        self.properties.get(property).map(|v| v.as_str())
+    }
+
     fn set(&mut self, property: &str, value: &str) {
         if let Some(val) = self.properties.get_mut(property) {
             *val = value.to_owned();
@@ -41,7 +46,16 @@ impl PropertiesFile {
         let mut file_path = dir_path.join(&self.filename);
         file_path = file_path.with_extension("properties");
         let file = File::create(file_path)?;
-        java_properties::write(file, &self.properties)?;
+
+        // Sort the properties so that the same properties always
+        // produce the same file
+        let mut properties: Vec<_> = self.properties.iter().collect();
+        properties.sort();
+        let mut writer = PropertiesWriter::new(BufWriter::new(file));
+        for (property, value) in properties {
+            writer.write(property, value)?;
+        }
+        writer.flush()?;
         Ok(())
     }
 }
@@ -79,6 +93,19 @@ impl PropertiesFileMap {
         })
     }
 
+    pub fn get_filenames(&self) -> Vec<&str> {
+        self.files.keys().map(|f| f.as_str()).collect()
+    }
+
+    pub fn get_properties(&self, filename: &str) -> Option<Vec<&str>> {
+        self.files.get(filename).map(|file|
+            file.properties.keys().map(|p| p.as_str()).collect())
+    }
+
+    pub fn get(&self, filename: &str, property: &str) -> Option<&str> {
+        self.files.get(filename).and_then(|file| file.get(property))
+    }
+
     pub fn set(&mut self, filename: &str, property: &str, value: &str) {
         if let Some(file) = self.files.get_mut(filename) {
             file.set(property, value);
@@ -90,7 +117,7 @@ impl PropertiesFileMap {
 
     pub fn output_to_dir(&self, dir_path: &Path) -> Result<()> {
         fs::create_dir_all(dir_path)?;
-        for (_, file) in &self.files {
+        for file in self.files.values() {
             file.output_to_file(dir_path)?;
         }
         Ok(())
@@ -99,10 +126,10 @@ impl PropertiesFileMap {
     pub fn get_vm_args(&self, prop_dir_path: &Path) -> Result<String> {
         let mut vm_args = String::new();
 
-        for (_, file) in &self.files {
+        for file in self.files.values() {
             vm_args.push_str("-D");
             vm_args.push_str(&file.id);
-            vm_args.push_str("=");
+            vm_args.push('=');
             let file_path = prop_dir_path.join(&file.filename);
             vm_args.push_str(file_path.to_str().unwrap());
             vm_args.push_str(".properties ");
@@ -0,0 +1,167 @@
+use crate::error::{Result, BenchError};
+
+const TOTAL: &str = "TOTAL";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxStatistics {
+    pub committed: u32,
+    pub aborted: u32,
+    // in milliseconds
+    pub avg_latency: f64
+}
+
+impl TxStatistics {
+    pub fn abort_rate(&self) -> f64 {
+        let total = self.committed + self.aborted;
+        if total == 0 {
+            0.0
+        } else {
+            self.aborted as f64 / total as f64
+        }
+    }
+
+    fn merge(&mut self, other: &TxStatistics) {
+        // Weight the latencies by the committed transactions
+        let committed = self.committed + other.committed;
+        if committed > 0 {
+            self.avg_latency = (self.avg_latency * self.committed as f64
+                + other.avg_latency * other.committed as f64) / committed as f64;
+        }
+        self.committed = committed;
+        self.aborted += other.aborted;
+    }
+}
+
+/// The result reported by VanillaBench in the result text file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    pub total: TxStatistics,
+    // (transaction type, statistics)
+    pub tx_types: Vec<(String, TxStatistics)>
+}
+
+impl BenchResult {
+    /// Parses the lines in format:
+    /// '[TX TYPE] - committed: XXXX, aborted: XXXX, avg latency: XXX ms'.
+    /// Other lines are ignored.
+    pub fn parse(text: &str) -> Result<BenchResult> {
+        let mut total = None;
+        let mut tx_types = Vec::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+            let (name, stats) = match line.find(" - committed") {
+                Some(pos) => (&line[.. pos], &line[pos + 3 ..]),
+                None => continue
+            };
+            let stats = parse_statistics(stats).ok_or_else(||
+                BenchError::Message(
+                    format!("cannot parse result line: {}", line)))?;
+
+            if name == TOTAL {
+                total = Some(stats);
+            } else {
+                tx_types.push((name.to_owned(), stats));
+            }
+        }
+
+        match total {
+            Some(total) => Ok(BenchResult {
+                total,
+                tx_types
+            }),
+            None => Err(BenchError::Message(
+                format!("cannot find the total result in: {}", text)))
+        }
+    }
+
+    /// Combines the results of multiple clients into one.
+    pub fn merge(results: &[BenchResult]) -> Option<BenchResult> {
+        let (first, others) = results.split_first()?;
+        let mut merged = first.clone();
+        for result in others {
+            merged.total.merge(&result.total);
+            for (name, stats) in &result.tx_types {
+                match merged.tx_types.iter_mut().find(|(n, _)| n == name) {
+                    Some((_, merged_stats)) => merged_stats.merge(stats),
+                    None => merged.tx_types.push((name.clone(), stats.clone()))
+                }
+            }
+        }
+        Some(merged)
+    }
+}
+
+// Input: 'committed: XXXX, aborted: XXXX, avg latency: XXX ms, ...'
+fn parse_statistics(text: &str) -> Option<TxStatistics> {
+    let mut committed = None;
+    let mut aborted = None;
+    let mut avg_latency = None;
+
+    for field in text.split(',') {
+        let mut pair = field.splitn(2, ':');
+        let key = pair.next()?.trim();
+        let value = pair.next()?.trim();
+        match key {
+            "committed" => committed = value.parse().ok(),
+            "aborted" => aborted = value.parse().ok(),
+            "avg latency" => avg_latency = value.trim_end_matches("ms")
+                .trim().parse().ok(),
+            _ => {}
+        }
+    }
+
+    Some(TxStatistics {
+        committed: committed?,
+        aborted: aborted?,
+        avg_latency: avg_latency?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESULT: &str = "\
+# of txns (including aborted) during benchmark period: 1130
+NEW_ORDER - committed: 500, aborted: 20, avg latency: 12 ms
+PAYMENT - committed: 600, aborted: 10, avg latency: 8.5 ms
+TOTAL - committed: 1100, aborted: 30, avg latency: 10 ms
+";
+
+    #[test]
+    fn test_parse() {
+        let result = BenchResult::parse(RESULT).unwrap();
+        assert_eq!(result.total, TxStatistics {
+            committed: 1100,
+            aborted: 30,
+            avg_latency: 10.0
+        });
+        assert_eq!(result.tx_types.len(), 2);
+        assert_eq!(result.tx_types[0].0, "NEW_ORDER");
+        assert_eq!(result.tx_types[1].1.avg_latency, 8.5);
+        assert!((result.tx_types[0].1.abort_rate() - 20.0 / 520.0).abs() < 1e-9);
+
+        assert!(BenchResult::parse("NEW_ORDER - committed: 1").is_err());
+        assert!(BenchResult::parse("nothing").is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let first = BenchResult::parse(RESULT).unwrap();
+        let second = BenchResult::parse("\
+NEW_ORDER - committed: 500, aborted: 0, avg latency: 20 ms
+DELIVERY - committed: 10, aborted: 0, avg latency: 30 ms
+TOTAL - committed: 510, aborted: 0, avg latency: 20 ms
+").unwrap();
+
+        let merged = BenchResult::merge(&[first, second]).unwrap();
+        assert_eq!(merged.total.committed, 1610);
+        assert_eq!(merged.total.aborted, 30);
+        assert_eq!(merged.tx_types.len(), 3);
+        assert_eq!(merged.tx_types[0].1.committed, 1000);
+        assert_eq!(merged.tx_types[0].1.avg_latency, 16.0);
+        assert_eq!(merged.tx_types[2].0, "DELIVERY");
+        assert!(BenchResult::merge(&[]).is_none());
+    }
+}
@@ -0,0 +1,147 @@
+use clap::{Arg, ArgMatches};
+
+use crate::error::{Result, BenchError};
+use crate::config::Machines;
+
+const ROLES: [&str; 3] = ["sequencer", "servers", "clients"];
+
+/// The machines chosen by `--role`, `--host` and `--exclude`.
+/// Every subcommand working on many machines takes these arguments.
+#[derive(Debug, Default)]
+pub struct HostSelection {
+    roles: Vec<String>,
+    hosts: Vec<String>,
+    excludes: Vec<String>
+}
+
+impl HostSelection {
+    pub fn args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+        vec![
+            Arg::with_name("ROLE")
+                .long("role")
+                .value_name("ROLE")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(true)
+                .possible_values(&ROLES)
+                .help("Only works on the machines of the role (can be repeated)"),
+            Arg::with_name("HOST")
+                .long("host")
+                .value_name("IP")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(true)
+                .help("Only works on the machine (can be repeated)"),
+            Arg::with_name("EXCLUDE")
+                .long("exclude")
+                .value_name("IP")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(true)
+                .help("Skips the machine (can be repeated)")
+        ]
+    }
+
+    pub fn from_args(args: &ArgMatches) -> HostSelection {
+        let values = |name| args.values_of(name)
+            .map(|values| values.map(|v| v.to_owned()).collect())
+            .unwrap_or_default();
+
+        HostSelection {
+            roles: values("ROLE"),
+            hosts: values("HOST"),
+            excludes: values("EXCLUDE")
+        }
+    }
+
+    /// Returns: the ips of the chosen machines without duplicates,
+    /// in the order of the sequencer, the servers and the clients
+    pub fn select(&self, machines: &Machines) -> Result<Vec<String>> {
+        let has_role = |role: &str| self.roles.is_empty()
+            || self.roles.iter().any(|r| r == role);
+
+        let mut candidates: Vec<&String> = Vec::new();
+        if has_role("sequencer") {
+            candidates.extend(&machines.sequencer);
+        }
+        if has_role("servers") {
+            candidates.extend(&machines.servers);
+        }
+        if has_role("clients") {
+            candidates.extend(&machines.clients);
+        }
+
+        for host in self.hosts.iter().chain(&self.excludes) {
+            if !machines.all.contains(host) {
+                return Err(BenchError::Message(
+                    format!("'{}' is not a machine in the config", host)));
+            }
+        }
+
+        let mut selected: Vec<String> = Vec::new();
+        for ip in candidates {
+            if selected.contains(ip) || self.excludes.contains(ip) {
+                continue;
+            }
+            if !self.hosts.is_empty() && !self.hosts.contains(ip) {
+                continue;
+            }
+            selected.push(ip.clone());
+        }
+
+        if selected.is_empty() {
+            return Err(BenchError::Message(
+                "no machine is selected".to_owned()));
+        }
+        Ok(selected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn machines() -> Machines {
+        let mut machines = Machines {
+            all: Vec::new(),
+            sequencer: Some("10.0.0.1".to_owned()),
+            servers: vec!["10.0.0.1".to_owned(), "10.0.0.2".to_owned()],
+            clients: vec!["10.0.0.3".to_owned(), "10.0.0.2".to_owned()],
+            hosts: Default::default(),
+            local_addresses: Default::default()
+        };
+        machines.all = ["10.0.0.1", "10.0.0.1", "10.0.0.2", "10.0.0.3", "10.0.0.2"]
+            .iter().map(|ip| ip.to_string()).collect();
+        machines
+    }
+
+    fn selection(roles: &[&str], hosts: &[&str], excludes: &[&str]) -> HostSelection {
+        let owned = |list: &[&str]| list.iter().map(|s| s.to_string()).collect();
+        HostSelection {
+            roles: owned(roles),
+            hosts: owned(hosts),
+            excludes: owned(excludes)
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let machines = machines();
+
+        assert_eq!(HostSelection::default().select(&machines).unwrap(),
+            ["10.0.0.1", "10.0.0.2", "10.0.0.3"]);
+        assert_eq!(selection(&["clients"], &[], &[]).select(&machines).unwrap(),
+            ["10.0.0.3", "10.0.0.2"]);
+        assert_eq!(selection(&["servers"], &[], &["10.0.0.1"]).select(&machines)
+            .unwrap(), ["10.0.0.2"]);
+        assert_eq!(selection(&[], &["10.0.0.3", "10.0.0.1"], &[]).select(&machines)
+            .unwrap(), ["10.0.0.1", "10.0.0.3"]);
+
+        assert!(selection(&["sequencer"], &["10.0.0.3"], &[]).select(&machines)
+            .is_err());
+        assert!(selection(&[], &["10.0.0.9"], &[]).select(&machines).is_err());
+    }
+}
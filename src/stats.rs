@@ -0,0 +1,94 @@
+// Two-sided 95% critical values of Student's t-distribution
+// for 1 to 30 degrees of freedom
+const T_TABLE_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+    2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+    2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042
+];
+const Z_95: f64 = 1.960;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub mean: f64,
+    pub stddev: f64,
+    pub min: f64,
+    pub max: f64,
+    // The 95% confidence interval of the mean
+    pub ci95_low: f64,
+    pub ci95_high: f64
+}
+
+impl Summary {
+    pub fn from_samples(samples: &[f64]) -> Option<Summary> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let count = samples.len();
+        let n = count as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let min = samples.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = samples.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+        // Use the sample standard deviation
+        let (stddev, margin) = if count > 1 {
+            let variance = samples.iter()
+                .map(|x| (x - mean) * (x - mean))
+                .sum::<f64>() / (n - 1.0);
+            let stddev = variance.sqrt();
+            (stddev, t_critical_95(count - 1) * stddev / n.sqrt())
+        } else {
+            (0.0, 0.0)
+        };
+
+        Some(Summary {
+            count,
+            mean,
+            stddev,
+            min,
+            max,
+            ci95_low: mean - margin,
+            ci95_high: mean + margin
+        })
+    }
+}
+
+fn t_critical_95(degrees_of_freedom: usize) -> f64 {
+    if degrees_of_freedom <= T_TABLE_95.len() {
+        T_TABLE_95[degrees_of_freedom - 1]
+    } else {
+        Z_95
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-3, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary = Summary::from_samples(&[10.0, 12.0, 14.0]).unwrap();
+        assert_eq!(summary.count, 3);
+        assert_close(summary.mean, 12.0);
+        assert_close(summary.stddev, 2.0);
+        assert_close(summary.min, 10.0);
+        assert_close(summary.max, 14.0);
+        // 4.303 * 2 / sqrt(3)
+        assert_close(summary.ci95_low, 12.0 - 4.969);
+        assert_close(summary.ci95_high, 12.0 + 4.969);
+    }
+
+    #[test]
+    fn test_single_sample() {
+        let summary = Summary::from_samples(&[5.0]).unwrap();
+        assert_close(summary.stddev, 0.0);
+        assert_close(summary.ci95_low, 5.0);
+        assert_close(summary.ci95_high, 5.0);
+        assert!(Summary::from_samples(&[]).is_none());
+    }
+}
@@ -1,10 +1,14 @@
 
+use std::sync::Arc;
+
 use log::*;
 use clap::{ArgMatches, Arg, App, SubCommand};
 
 use crate::error::Result;
 use crate::config::Config;
-use crate::command;
+use crate::transport::Transport;
+use crate::parallel;
+use crate::selection::HostSelection;
 
 pub fn get_sub_command<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name("all-exec")
@@ -12,17 +16,25 @@ pub fn get_sub_command<'a, 'b>() -> App<'a, 'b> {
                     .help("The command to execute on all the machines")
                     .required(true)
                     .index(1))
+                .arg(parallel::parallelism_arg())
+                .args(&HostSelection::args())
                 .about("executes the given command on all the machines")
 }
 
-pub fn execute(config: &Config, args: &ArgMatches) -> Result<()> {
+pub fn execute(config: &Config, transport: &Arc<dyn Transport>,
+        args: &ArgMatches) -> Result<()> {
     let cmd = args.value_of("COMMAND").unwrap();
+    let parallelism = parallel::get_parallelism(args)?;
 
-    for ip in &config.machines.all {
-        info!("Executing the command on {}", &ip);
-        let output = command::ssh(&config.system.user_name, &ip, &cmd)?;
-        println!("{}", output);
-    }
+    let ips = HostSelection::from_args(args).select(&config.machines)?;
+    info!("Executing the command on {} machines...", ips.len());
+    let outcomes = parallel::for_each_host(&ips, parallelism,
+        |ip| {
+            let output = transport.exec(ip, cmd)?;
+            parallel::print_with_host(ip, &output);
+            Ok(())
+        });
 
-    Ok(())
+    parallel::print_summary(&outcomes);
+    parallel::check(&outcomes)
 }
\ No newline at end of file
@@ -1,17 +1,26 @@
 
 use std::path::{Path, PathBuf};
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::collections::BTreeMap;
+use std::sync::Arc;
 
 use colored::*;
 use log::*;
 use clap::{ArgMatches, Arg, App, SubCommand};
 use chrono::prelude::*;
 
-use crate::error::Result;
+use crate::error::{Result, BenchError};
 use crate::config::Config;
-use crate::parameters::{Parameter, ParameterList};
+use crate::parameters::Parameter;
 use crate::connections::Action;
+use crate::ledger::{JobLedger, JobStatus};
+use crate::stats::Summary;
+use crate::results::BenchResult;
+use crate::transport::Transport;
+use crate::interrupt;
+
+const MAIN_REPORT_FILENAME: &str = "throughput.csv";
+const TX_REPORT_FILENAME: &str = "transactions.csv";
 
 pub fn get_sub_command<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name("bench")
@@ -27,64 +36,168 @@ pub fn get_sub_command<'a, 'b>() -> App<'a, 'b> {
                     .long("ignore-error")
                     .short("i")
                     .help("If there is an error happens in a job, do not stop and proceed to the next job."))
+                .arg(Arg::with_name("COMPRESS LOGS")
+                    .long("compress-logs")
+                    .short("z")
+                    .help("Compresses the logs kept in the report directory of each job with gzip"))
+                .arg(Arg::with_name("NO VALIDATION")
+                    .long("no-validation")
+                    .help("Does not check if the properties in the parameter file exist"))
+                .arg(Arg::with_name("RESUME")
+                    .long("resume")
+                    .short("r")
+                    .value_name("REPORT DIR")
+                    .takes_value(true)
+                    .help("Resumes the campaign recorded in the given report directory. Finished jobs are skipped."))
+                .arg(Arg::with_name("REPETITIONS")
+                    .long("repetitions")
+                    .short("n")
+                    .value_name("N")
+                    .takes_value(true)
+                    .help("Runs each job N times. Overrides 'repetitions' in the parameter file."))
                 .about("running the benchmarks using the given parameters")
 }
 
-pub fn execute(config: &Config, args: &ArgMatches) -> Result<()> {
+pub fn execute(config: &Config, transport: &Arc<dyn Transport>,
+        args: &ArgMatches) -> Result<()> {
     let db_name = args.value_of("DB NAME").unwrap();
     let param_file = args.value_of("PARAMETER FILE").unwrap();
     let ignore_error = args.is_present("IGNORE ERROR");
+    let compress_logs = args.is_present("COMPRESS LOGS");
+    let resume_dir = args.value_of("RESUME");
+    let repetitions_arg: Option<usize> = match args.value_of("REPETITIONS") {
+        Some(n) => Some(n.parse()?),
+        None => None
+    };
     
+    interrupt::install_handler();
+
     info!("Preparing for running benchmarks...");
     info!("Using parameter file '{}'", param_file);
 
     // TODO: Check if the database exists
 
     // Read the parameter file
-    let param_list = ParameterList::from_file(Path::new(param_file))?;
+    let param_list = super::read_parameter_file(param_file,
+        !args.is_present("NO VALIDATION"))?;
     let param_list = param_list.to_vec();
     info!("Analyzing parameter file finished. {} jobs to run.",
             param_list.len().to_string().cyan());
 
     // Prepare for the final report
-    let main_report_dir = create_report_dir()?;
-    let mut writer = get_main_report_writer(&main_report_dir)?;
-    write_csv_header(&mut writer, &param_list[0])?;
+    let (main_report_dir, mut ledger, mut writer, mut tx_writer) = match resume_dir {
+        Some(dir) => {
+            info!("Resuming the campaign in '{}'", dir);
+            let main_report_dir = PathBuf::from(dir);
+            let ledger = JobLedger::open(&main_report_dir)?;
+            let writer = get_report_writer(&main_report_dir,
+                MAIN_REPORT_FILENAME, true)?;
+            let tx_writer = get_report_writer(&main_report_dir,
+                TX_REPORT_FILENAME, true)?;
+            (main_report_dir, ledger, writer, tx_writer)
+        },
+        None => {
+            let main_report_dir = create_report_dir()?;
+            let ledger = JobLedger::create(&main_report_dir)?;
+            let mut writer = get_report_writer(&main_report_dir,
+                MAIN_REPORT_FILENAME, false)?;
+            write_csv_header(&mut writer, &param_list[0])?;
+            let mut tx_writer = get_report_writer(&main_report_dir,
+                TX_REPORT_FILENAME, false)?;
+            tx_writer.write_record(["job_id", "trial", "tx_type", "committed",
+                "aborted", "abort_rate", "avg_latency"])?;
+            (main_report_dir, ledger, writer, tx_writer)
+        }
+    };
 
     // Running jobs
-    for job_id in 0 .. param_list.len() {
-        info!("Running job {}...", job_id);
-
-        let job_report_dir = create_job_dir(&main_report_dir, job_id)?;
-
-        let throughput_str = match super::run(
-            config, &param_list[job_id],
-            &db_name, Action::Benchmarking, Some(job_report_dir.display().to_string())
-        ) {
-            Ok(ths) => {
-                let mut total_throughput = 0;
-                for th in ths {
-                    total_throughput += th.unwrap();
+    for (job_id, parameter) in param_list.iter().enumerate() {
+        let param_hash = parameter.hash();
+        let repetitions = get_repetitions(parameter, repetitions_arg)?;
+        let mut has_new_trials = false;
+
+        for trial in 0 .. repetitions {
+            let job_name = if repetitions > 1 {
+                format!("{} (trial {})", job_id, trial)
+            } else {
+                job_id.to_string()
+            };
+
+            if let Some(record) = ledger.get(job_id, trial) {
+                if record.param_hash != param_hash {
+                    return Err(BenchError::Message(format!(
+                        "the parameters of job {} do not match the ones in the report directory",
+                        job_id)));
                 }
-                info!("Job {} finished successfully.", job_id);
-                info!("The total throughput of job {} is {}.", job_id, total_throughput);
-                total_throughput.to_string()
-            },
-            Err(e) => {
-                info!("Job {} finished with an error: {}", job_id, e);
-
-                if ignore_error {
-                    "error".to_owned()
-                } else {
-                    return Err(e);
+                if record.status == JobStatus::Finished {
+                    info!("Job {} has finished before. Skipped.", job_name);
+                    continue;
                 }
             }
-        };
 
-        info!("Writing the result to the report...");
-        aggregate_results(&main_report_dir, job_id)?;
-        write_report(&mut writer, job_id, &param_list[job_id], &throughput_str)?;
-        info!("Finished writing the result of job {}", job_id);
+            info!("Running job {}...", job_name);
+            has_new_trials = true;
+            ledger.start_job(job_id, trial, &param_hash)?;
+
+            let dir_name = trial_dir_name(job_id, trial, repetitions);
+            let trial_report_dir = create_job_dir(&main_report_dir, &dir_name)?;
+
+            let result = match super::run(
+                config, transport, parameter,
+                db_name, Action::Benchmarking, Some(trial_report_dir.display().to_string()),
+                compress_logs
+            ).and_then(merge_client_results) {
+                Ok(result) => {
+                    let total_throughput = result.total.committed;
+                    info!("Job {} finished successfully.", job_name);
+                    info!("The total throughput of job {} is {}.", job_name, total_throughput);
+                    result
+                },
+                Err(BenchError::Interrupted) => {
+                    warn!("Job {} is interrupted.", job_name);
+                    ledger.interrupt_job(job_id, trial)?;
+                    return Err(BenchError::Interrupted);
+                },
+                Err(e) => {
+                    info!("Job {} finished with an error: {}", job_name, e);
+                    if let BenchError::Timeout(_) = e {
+                        ledger.time_out_job(job_id, trial)?;
+                    } else {
+                        ledger.fail_job(job_id, trial)?;
+                    }
+
+                    // The failed jobs have no row in the report, so
+                    // they can be run again with `--resume`
+                    if ignore_error {
+                        continue;
+                    } else {
+                        return Err(e);
+                    }
+                }
+            };
+
+            info!("Writing the result to the report...");
+            aggregate_results(&main_report_dir, &dir_name)?;
+            write_report(&mut writer, job_id, &trial.to_string(), parameter,
+                result_columns(&result))?;
+            write_tx_report(&mut tx_writer, job_id, trial, &result)?;
+            // Marked as finished only after the rows are written, or
+            // a resumed campaign would skip a job without a row
+            ledger.finish_job(job_id, trial, result.total.committed)?;
+            info!("Finished writing the result of job {}", job_name);
+        }
+
+        // Summarize the trials of the job once all of them finished
+        let samples: Vec<f64> = ledger.finished_throughputs(job_id)
+            .into_iter().map(f64::from).collect();
+        if repetitions > 1 && has_new_trials && samples.len() == repetitions {
+            if let Some(summary) = Summary::from_samples(&samples) {
+                info!("The mean throughput of job {} is {:.2} (stddev: {:.2}, {} trials).",
+                    job_id, summary.mean, summary.stddev, summary.count);
+                write_report(&mut writer, job_id, "all", parameter,
+                    summary_columns(&summary))?;
+            }
+        }
     }
 
     // Show the final result (where is the database, the size...)
@@ -105,18 +218,49 @@ fn create_report_dir() -> Result<PathBuf> {
     Ok(report_dir_path)
 }
 
-fn create_job_dir(main_report_dir: &Path, job_id: usize) -> Result<PathBuf> {
-    let job_dir = main_report_dir.join(&format!("job-{}", job_id));
+pub fn get_repetitions(parameter: &Parameter, repetitions_arg: Option<usize>)
+        -> Result<usize> {
+    let repetitions = match repetitions_arg {
+        Some(n) => n,
+        None => match parameter.get_autobencher_param("repetitions") {
+            Ok(n) => n.parse()?,
+            Err(_) => 1
+        }
+    };
+
+    if repetitions == 0 {
+        return Err(BenchError::Message(
+            "the number of repetitions must be at least 1".to_owned()));
+    }
+    Ok(repetitions)
+}
+
+// Each trial has its own directory. The trials are grouped under
+// the directory of the job if the job is repeated.
+fn trial_dir_name(job_id: usize, trial: usize, repetitions: usize) -> String {
+    if repetitions > 1 {
+        format!("job-{}/trial-{}", job_id, trial)
+    } else {
+        format!("job-{}", job_id)
+    }
+}
+
+fn create_job_dir(main_report_dir: &Path, dir_name: &str) -> Result<PathBuf> {
+    let job_dir = main_report_dir.join(dir_name);
+    // Remove the results of the previous trial that did not finish
+    if job_dir.exists() {
+        std::fs::remove_dir_all(&job_dir)?;
+    }
     std::fs::create_dir_all(&job_dir)?;
     Ok(job_dir)
 }
 
-fn aggregate_results(main_dir: &Path, job_id: usize) -> Result<()> {
+fn aggregate_results(main_dir: &Path, dir_name: &str) -> Result<()> {
     // Prepare variables
     let mut timeline: BTreeMap<usize, usize> = BTreeMap::new();
 
     // Open each csv files
-    let job_dir = main_dir.join(&format!("job-{}", job_id));
+    let job_dir = main_dir.join(dir_name);
     for entry in std::fs::read_dir(job_dir)? {
         let filepath = entry?.path();
         if filepath.is_file() && filepath.extension().unwrap() == "csv" {
@@ -140,47 +284,103 @@ fn aggregate_results(main_dir: &Path, job_id: usize) -> Result<()> {
     }
 
     // Write to an output file
-    let timeline_filename = main_dir.join(&format!("job-{}-timeline.csv", job_id));
+    let timeline_filename = main_dir.join(format!("{}-timeline.csv", dir_name));
     let mut writer = csv::Writer::from_path(timeline_filename)?;
-    writer.write_record(&["time", "throughput"])?;
+    writer.write_record(["time", "throughput"])?;
     for (time, throughput) in timeline {
-        writer.write_record(&[time.to_string(), throughput.to_string()])?;
+        writer.write_record([time.to_string(), throughput.to_string()])?;
     }
     writer.flush()?;
 
     Ok(())
 }
 
-fn get_main_report_writer(report_dir: &Path) -> Result<csv::Writer<File>> {
-    let file_path = report_dir.join("throughput.csv");
-    Ok(csv::Writer::from_path(file_path)?)
+fn merge_client_results(results: Vec<Option<BenchResult>>) -> Result<BenchResult> {
+    let results: Vec<BenchResult> = results.into_iter().flatten().collect();
+    BenchResult::merge(&results).ok_or_else(|| BenchError::Message(
+        "no client reports a result".to_owned()))
+}
+
+fn get_report_writer(report_dir: &Path, filename: &str, append: bool)
+        -> Result<csv::Writer<File>> {
+    let file_path = report_dir.join(filename);
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(append)
+        .truncate(!append)
+        .open(file_path)?;
+    Ok(csv::Writer::from_writer(file))
 }
 
 fn write_csv_header(writer: &mut csv::Writer<File>,
         parameter: &Parameter) -> Result<()> {
     
     let properties = parameter.get_properties();
-    let mut headers = vec!["job_id"];
+    let mut headers = vec!["job_id", "trial"];
     let mut params: Vec<_> = properties.iter()
-        .map(|p| p.split(".").last().unwrap()).collect();
+        .map(|p| p.split('.').next_back().unwrap()).collect();
     headers.append(&mut params);
-    headers.push("throughput");
+    headers.extend_from_slice(&["throughput", "aborted", "abort_rate",
+        "avg_latency"]);
+    headers.extend_from_slice(&["mean", "stddev", "min", "max",
+        "ci95_low", "ci95_high"]);
     writer.write_record(headers)?;
 
     Ok(())
 }
 
-fn write_report(writer: &mut csv::Writer<File>, job_id: usize,
-        parameter: &Parameter, throughput_str: &str) -> Result<()> {
+fn write_report(writer: &mut csv::Writer<File>, job_id: usize, trial: &str,
+        parameter: &Parameter, mut result_columns: Vec<String>) -> Result<()> {
     let job_id = job_id.to_string();
-    let mut values: Vec<&str> = vec![];
-    let mut params = parameter.get_properties_values();
+    let mut values: Vec<String> = vec![job_id, trial.to_owned()];
+    values.extend(parameter.get_properties_values().iter()
+        .map(|v| v.to_string()));
+    values.append(&mut result_columns);
 
-    values.push(&job_id);
-    values.append(&mut params);
-    values.push(throughput_str);
     writer.write_record(values)?;
     writer.flush()?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// Columns: throughput, aborted, abort_rate, avg_latency and empty statistics
+fn result_columns(result: &BenchResult) -> Vec<String> {
+    let mut columns = vec![
+        result.total.committed.to_string(),
+        result.total.aborted.to_string(),
+        format!("{:.4}", result.total.abort_rate()),
+        format!("{:.2}", result.total.avg_latency)
+    ];
+    columns.extend(std::iter::repeat_n(String::new(), 6));
+    columns
+}
+
+// Columns: mean throughput, empty result details and the statistics
+fn summary_columns(summary: &Summary) -> Vec<String> {
+    let mut columns = vec![format!("{:.2}", summary.mean)];
+    columns.extend(std::iter::repeat_n(String::new(), 3));
+    for stat in &[summary.mean, summary.stddev, summary.min, summary.max,
+            summary.ci95_low, summary.ci95_high] {
+        columns.push(format!("{:.2}", stat));
+    }
+    columns
+}
+
+fn write_tx_report(writer: &mut csv::Writer<File>, job_id: usize, trial: usize,
+        result: &BenchResult) -> Result<()> {
+    for (tx_type, stats) in &result.tx_types {
+        writer.write_record([
+            job_id.to_string(),
+            trial.to_string(),
+            tx_type.clone(),
+            stats.committed.to_string(),
+            stats.aborted.to_string(),
+            format!("{:.4}", stats.abort_rate()),
+            format!("{:.2}", stats.avg_latency)
+        ])?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
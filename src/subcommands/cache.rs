@@ -0,0 +1,55 @@
+
+use std::sync::Arc;
+
+use log::*;
+use clap::{ArgMatches, Arg, App, SubCommand};
+
+use crate::error::Result;
+use crate::config::Config;
+use crate::transport::Transport;
+use crate::jar_cache;
+use crate::selection::HostSelection;
+
+const DEFAULT_KEEP: &str = "4";
+
+pub fn get_sub_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("cache")
+                .subcommand(SubCommand::with_name("prune")
+                    .arg(Arg::with_name("KEEP")
+                        .long("keep")
+                        .short("k")
+                        .value_name("N")
+                        .takes_value(true)
+                        .default_value(DEFAULT_KEEP)
+                        .help("The number of the most recently used jars to keep on each machine"))
+                    .args(&HostSelection::args())
+                    .about("removes the old jars in the jar caches of all the machines"))
+                .about("manages the jar caches on the machines")
+}
+
+pub fn execute(config: &Config, transport: &Arc<dyn Transport>,
+        args: &ArgMatches) -> Result<()> {
+    if let Some(matches) = args.subcommand_matches("prune") {
+        prune(config, transport, matches)?;
+    }
+    Ok(())
+}
+
+fn prune(config: &Config, transport: &Arc<dyn Transport>,
+        args: &ArgMatches) -> Result<()> {
+    let keep: usize = args.value_of("KEEP").unwrap().parse()?;
+    let ips = HostSelection::from_args(args).select(&config.machines)?;
+
+    let mut total = 0;
+    for ip in &ips {
+        let remote_work_dir = config.work_dir(ip);
+        let remote_bench_dir = format!("{}/benchmarker", remote_work_dir);
+        let removed = jar_cache::prune(transport.as_ref(), ip, remote_work_dir,
+            &remote_bench_dir, keep)?;
+        info!("Removed {} cached jars on {}", removed, ip);
+        total += removed;
+    }
+    info!("Removed {} cached jars in total", total);
+
+    Ok(())
+}
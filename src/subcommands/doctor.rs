@@ -0,0 +1,472 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use clap::{ArgMatches, Arg, App, SubCommand};
+use colored::*;
+use log::*;
+
+use crate::error::{Result, BenchError};
+use crate::config::Config;
+use crate::connections::{Action, ConnectionInfo, Role};
+use crate::parameters::ParameterList;
+use crate::properties::PropertiesFileMap;
+use crate::transport::Transport;
+use crate::jar_cache;
+use crate::parallel;
+use crate::selection::HostSelection;
+
+// The databases and the results are written in the working directories
+const WARN_FREE_DISK_MB: u64 = 10 * 1024;
+const FAIL_FREE_DISK_MB: u64 = 1024;
+const WARN_CLOCK_SKEW_SECS: f64 = 1.0;
+const FAIL_CLOCK_SKEW_SECS: f64 = 5.0;
+// The ports checked on each machine if no parameter file is given
+const DEFAULT_PORT_COUNT: usize = 10;
+const JAVA_VERSION: u32 = 8;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Status {
+    Pass,
+    Warn,
+    Fail
+}
+
+#[derive(Debug)]
+struct Check {
+    name: &'static str,
+    // "local" or the ip of a machine
+    target: String,
+    status: Status,
+    detail: String
+}
+
+impl Check {
+    fn new<S: Into<String>>(name: &'static str, target: &str, status: Status,
+            detail: S) -> Check {
+        Check {
+            name,
+            target: target.to_owned(),
+            status,
+            detail: detail.into()
+        }
+    }
+}
+
+pub fn get_sub_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("doctor")
+                .arg(Arg::with_name("PARAMETER FILE")
+                    .help("Also checks the jars and the ports used by the jobs in the file")
+                    .index(1))
+                .arg(parallel::parallelism_arg())
+                .args(&HostSelection::args())
+                .about("checks the config, the local files and the machines")
+}
+
+pub fn execute(config: &Config, transport: &Arc<dyn Transport>,
+        args: &ArgMatches) -> Result<()> {
+    let ips = HostSelection::from_args(args).select(&config.machines)?;
+    let parallelism = parallel::get_parallelism(args)?;
+
+    info!("Checking the local files...");
+    let mut checks = check_local_files(config, &ips);
+
+    // The placement of the processes is only known with a parameter file
+    let mut ports: HashMap<String, BTreeSet<usize>> = HashMap::new();
+    if let Some(path) = args.value_of("PARAMETER FILE") {
+        match read_jobs(config, path) {
+            Ok(jobs) => {
+                checks.extend(check_jobs(&jobs));
+                for (_, conns) in &jobs {
+                    for conn in conns {
+                        ports.entry(conn.ip.clone()).or_default().insert(conn.port);
+                    }
+                }
+            },
+            Err(e) => checks.push(Check::new("parameters", "local",
+                Status::Fail, e.to_string()))
+        }
+    }
+
+    info!("Checking {} machines...", ips.len());
+    let remote_checks: Mutex<HashMap<String, Vec<Check>>> = Mutex::new(HashMap::new());
+    parallel::for_each_host(&ips, parallelism, |ip| {
+        let ports = ports.get(ip).cloned()
+            .unwrap_or_else(|| default_ports(config, ip));
+        let host_checks = check_machine(config, transport.as_ref(), ip, &ports);
+        remote_checks.lock().unwrap().insert(ip.to_owned(), host_checks);
+        Ok(())
+    });
+    let mut remote_checks = remote_checks.into_inner().unwrap();
+    for ip in &ips {
+        checks.extend(remote_checks.remove(ip).unwrap_or_default());
+    }
+
+    print_checks(&checks);
+
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    let (warnings, failures) = (count(Status::Warn), count(Status::Fail));
+    if failures > 0 {
+        return Err(BenchError::Message(format!("{} of {} checks failed",
+            failures, checks.len())));
+    }
+    info!("All the checks passed with {} warnings", warnings);
+    Ok(())
+}
+
+fn check_local_files(config: &Config, ips: &[String]) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    checks.push(match PropertiesFileMap::from_dir(Path::new("properties")) {
+        Ok(files) => Check::new("properties", "local", Status::Pass,
+            format!("{} files", files.get_filenames().len())),
+        Err(e) => Check::new("properties", "local", Status::Fail, e.to_string())
+    });
+
+    // Only init-env needs it, and only for the machines without the JDK
+    if ips.iter().any(|ip| config.uses_deployed_jdk(ip)) {
+        let path = &config.jdk.package_path;
+        checks.push(if Path::new(path).is_file() {
+            Check::new("jdk package", "local", Status::Pass, path.as_str())
+        } else {
+            Check::new("jdk package", "local", Status::Warn,
+                format!("cannot find {}, so init-env cannot deploy the JDK", path))
+        });
+    }
+
+    checks
+}
+
+// Output: the jar directory and the processes of each job
+fn read_jobs(config: &Config, path: &str)
+        -> Result<Vec<(String, Vec<ConnectionInfo>)>> {
+    let param_list: ParameterList = super::read_parameter_file(path, true)?;
+
+    let mut jobs = Vec::new();
+    for parameter in param_list.to_vec() {
+        let jar_dir = parameter.get_autobencher_param("jar_dir")?.to_owned();
+        let (sequencer, servers, clients) = super::generate_connection_list(
+            config, &parameter, Action::Benchmarking)?;
+        let conns = sequencer.into_iter().chain(servers).chain(clients).collect();
+        jobs.push((jar_dir, conns));
+    }
+    Ok(jobs)
+}
+
+fn check_jobs(jobs: &[(String, Vec<ConnectionInfo>)]) -> Vec<Check> {
+    let mut checks = vec![Check::new("parameters", "local", Status::Pass,
+        format!("{} jobs", jobs.len()))];
+
+    let jar_dirs: BTreeSet<&str> = jobs.iter().map(|(dir, _)| dir.as_str()).collect();
+    for dir in jar_dirs {
+        let path = Path::new("jars").join(dir);
+        checks.push(match jar_cache::read_jars(&path) {
+            Ok(_) => Check::new("jars", "local", Status::Pass,
+                path.display().to_string()),
+            Err(e) => Check::new("jars", "local", Status::Fail,
+                format!("{}: {}", path.display(), e))
+        });
+    }
+
+    let mut clashes = BTreeSet::new();
+    for (_, conns) in jobs {
+        clashes.extend(find_port_clashes(conns));
+    }
+    checks.push(if clashes.is_empty() {
+        Check::new("placement", "local", Status::Pass, "no port is shared")
+    } else {
+        Check::new("placement", "local", Status::Fail, format!(
+            "processes share {}", clashes.into_iter().collect::<Vec<_>>().join(", ")))
+    });
+
+    checks
+}
+
+// Output: the addresses taken by more than one process
+fn find_port_clashes(conns: &[ConnectionInfo]) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    let mut clashes = BTreeSet::new();
+    for conn in conns {
+        let address = format!("{}:{}", conn.ip, conn.port);
+        if !seen.insert(address.clone()) {
+            clashes.insert(address);
+        }
+    }
+    clashes.into_iter().collect()
+}
+
+// Without a parameter file, the first ports of the ranges of the roles
+// that the machine has are checked
+fn default_ports(config: &Config, ip: &str) -> BTreeSet<usize> {
+    let machines = &config.machines;
+    let roles = [
+        (machines.sequencer.as_deref() == Some(ip), &config.ports.sequencer),
+        (machines.servers.iter().any(|s| s == ip), &config.ports.servers),
+        (machines.clients.iter().any(|c| c == ip), &config.ports.clients)
+    ];
+
+    let mut ports = BTreeSet::new();
+    for (has_role, range) in roles.iter() {
+        if *has_role {
+            let last = range.last.min(range.first + DEFAULT_PORT_COUNT - 1);
+            ports.extend(range.first ..= last);
+        }
+    }
+    ports
+}
+
+fn check_machine(config: &Config, transport: &dyn Transport, ip: &str,
+        ports: &BTreeSet<usize>) -> Vec<Check> {
+    let start = Instant::now();
+    if let Err(e) = transport.exec(ip, "true") {
+        // The other checks cannot run either
+        return vec![Check::new("connection", ip, Status::Fail, first_line(&e))];
+    }
+    let mut checks = vec![
+        Check::new("connection", ip, Status::Pass,
+            format!("{} ms", start.elapsed().as_millis())),
+        check_java(config, transport, ip),
+        check_disk(config, transport, ip),
+        check_ports(transport, ip, ports),
+        check_clock(transport, ip)
+    ];
+    checks.extend(check_pinning(config, transport, ip));
+    checks
+}
+
+// Checks the programs pinning the processes of the roles of the machine
+fn check_pinning(config: &Config, transport: &dyn Transport, ip: &str) -> Option<Check> {
+    let (machines, pinning) = (&config.machines, &config.pinning);
+    let mut settings = Vec::new();
+    if machines.sequencer.as_deref() == Some(ip) {
+        settings.push(pinning.sequencer.clone());
+    }
+    let roles = [(Role::Server, &machines.servers, &pinning.servers),
+        (Role::Client, &machines.clients, &pinning.clients)];
+    for (role, ips, role_pinning) in roles.iter() {
+        if ips.iter().any(|i| i == ip) {
+            settings.push(role_pinning.common.clone());
+            for id in role_pinning.processes.keys() {
+                settings.push(pinning.settings(*role, id.parse().unwrap_or_default()));
+            }
+        }
+    }
+
+    let programs: BTreeSet<&str> = settings.iter().filter_map(|s| s.program()).collect();
+    if programs.is_empty() {
+        return None;
+    }
+    let missing: Vec<&str> = programs.iter().copied()
+        .filter(|program| transport.exec(ip, &format!("command -v {}", program)).is_err())
+        .collect();
+    Some(if missing.is_empty() {
+        Check::new("pinning", ip, Status::Pass,
+            programs.into_iter().collect::<Vec<_>>().join(", "))
+    } else {
+        Check::new("pinning", ip, Status::Fail,
+            format!("{} not installed", missing.join(", ")))
+    })
+}
+
+fn check_java(config: &Config, transport: &dyn Transport, ip: &str) -> Check {
+    // Run it in the same way as the processes
+    let cmd = format!("cd {} && {} -version 2>&1", config.work_dir(ip),
+        config.java_bin(ip));
+    let output = match transport.exec(ip, &cmd) {
+        Ok(output) => output,
+        Err(e) => {
+            let hint = if config.uses_deployed_jdk(ip) { " (run init-env)" } else { "" };
+            return Check::new("java", ip, Status::Fail,
+                format!("{}{}", first_line(&e), hint));
+        }
+    };
+
+    match parse_java_version(&output) {
+        Some((version, major)) if major == JAVA_VERSION =>
+            Check::new("java", ip, Status::Pass, version),
+        Some((version, _)) => Check::new("java", ip, Status::Warn,
+            format!("{} (the benchmarks are built for Java {})", version, JAVA_VERSION)),
+        None => Check::new("java", ip, Status::Warn,
+            format!("unknown version: {}", output.lines().next().unwrap_or("")))
+    }
+}
+
+// Output: the version string and the major version, e.g. ("1.8.0_211", 8)
+fn parse_java_version(output: &str) -> Option<(String, u32)> {
+    let start = output.find("version \"")? + "version \"".len();
+    let version = &output[start ..];
+    let version = &version[.. version.find('"')?];
+
+    // Java 8 and before are named 1.x
+    let mut numbers = version.split(|c: char| !c.is_ascii_digit());
+    let major = match numbers.next()?.parse().ok()? {
+        1 => numbers.next()?.parse().ok()?,
+        major => major
+    };
+    Some((version.to_owned(), major))
+}
+
+fn check_disk(config: &Config, transport: &dyn Transport, ip: &str) -> Check {
+    let work_dir = config.work_dir(ip);
+    let cmd = format!("df -Pk {}", work_dir);
+    let free_mb = match transport.exec(ip, &cmd) {
+        Ok(output) => parse_free_disk_mb(&output),
+        Err(e) => return Check::new("disk", ip, Status::Fail,
+            format!("{} (run init-env to create {})", first_line(&e), work_dir))
+    };
+
+    match free_mb {
+        Some(mb) => {
+            let status = if mb < FAIL_FREE_DISK_MB {
+                Status::Fail
+            } else if mb < WARN_FREE_DISK_MB {
+                Status::Warn
+            } else {
+                Status::Pass
+            };
+            Check::new("disk", ip, status,
+                format!("{:.1} GB free in {}", mb as f64 / 1024.0, work_dir))
+        },
+        None => Check::new("disk", ip, Status::Warn, "cannot read the output of df")
+    }
+}
+
+// Reads the available space in the POSIX output of `df -Pk`
+fn parse_free_disk_mb(output: &str) -> Option<u64> {
+    let line = output.lines().nth(1)?;
+    let available_kb: u64 = line.split_whitespace().nth(3)?.parse().ok()?;
+    Some(available_kb / 1024)
+}
+
+fn check_ports(transport: &dyn Transport, ip: &str, ports: &BTreeSet<usize>) -> Check {
+    let output = match transport.exec(ip, "ss -Htln 2>/dev/null || netstat -tln") {
+        Ok(output) => output,
+        Err(e) => return Check::new("ports", ip, Status::Warn,
+            format!("cannot list the listening ports: {}", first_line(&e)))
+    };
+
+    let range = match (ports.iter().next(), ports.iter().next_back()) {
+        (Some(first), Some(last)) if first != last => format!("{}-{}", first, last),
+        (Some(port), _) => port.to_string(),
+        _ => "no port".to_owned()
+    };
+    let busy: Vec<String> = parse_listening_ports(&output, ip).into_iter()
+        .filter(|port| ports.contains(port))
+        .map(|port| port.to_string())
+        .collect();
+    if busy.is_empty() {
+        Check::new("ports", ip, Status::Pass, format!("{} free", range))
+    } else {
+        // The processes left by the last job are killed before running
+        // a new one, but the other programs are not
+        Check::new("ports", ip, Status::Warn, format!("{} in use", busy.join(", ")))
+    }
+}
+
+// Reads the ports listened on the ip (or all the addresses) from the
+// output of `ss -tln` or `netstat -tln`. Both put the local address at
+// the fourth column.
+fn parse_listening_ports(output: &str, ip: &str) -> BTreeSet<usize> {
+    let mut ports = BTreeSet::new();
+    for line in output.lines() {
+        let address = match line.split_whitespace().nth(3) {
+            Some(address) => address,
+            None => continue
+        };
+        let (host, port) = match address.rfind(':') {
+            Some(idx) => (&address[.. idx], &address[idx + 1 ..]),
+            None => continue
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let is_ours = host == ip || ["*", "0.0.0.0", "::", ""].contains(&host);
+        if let (true, Ok(port)) = (is_ours, port.parse()) {
+            ports.insert(port);
+        }
+    }
+    ports
+}
+
+fn check_clock(transport: &dyn Transport, ip: &str) -> Check {
+    let before = now_secs();
+    let output = match transport.exec(ip, "date +%s.%N") {
+        Ok(output) => output,
+        Err(e) => return Check::new("clock", ip, Status::Warn, first_line(&e))
+    };
+    let after = now_secs();
+
+    // The time is read around the middle of the round trip
+    let remote: f64 = match output.trim().parse() {
+        Ok(time) => time,
+        Err(_) => return Check::new("clock", ip, Status::Warn,
+            format!("cannot read the time: {}", output.trim()))
+    };
+    let skew = remote - (before + after) / 2.0;
+
+    let status = if skew.abs() >= FAIL_CLOCK_SKEW_SECS {
+        Status::Fail
+    } else if skew.abs() >= WARN_CLOCK_SKEW_SECS {
+        Status::Warn
+    } else {
+        Status::Pass
+    };
+    Check::new("clock", ip, status, format!("{:+.3}s from local (±{:.3}s)",
+        skew, (after - before) / 2.0))
+}
+
+fn now_secs() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs_f64()
+}
+
+fn first_line(error: &BenchError) -> String {
+    error.to_string().lines().next().unwrap_or("").to_owned()
+}
+
+fn print_checks(checks: &[Check]) {
+    let rows: Vec<Vec<ColoredString>> = checks.iter().map(|check| {
+        let status = match check.status {
+            Status::Pass => "pass".green(),
+            Status::Warn => "warn".yellow(),
+            Status::Fail => "fail".red()
+        };
+        vec![check.name.normal(), check.target.normal(), status,
+            check.detail.normal()]
+    }).collect();
+    parallel::print_table(&["CHECK", "TARGET", "STATUS", "DETAIL"], &rows);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_java_version() {
+        let java8 = "java version \"1.8.0_211\"\nJava(TM) SE Runtime Environment";
+        assert_eq!(parse_java_version(java8), Some(("1.8.0_211".to_owned(), 8)));
+        let java17 = "openjdk version \"17.0.2\" 2022-01-18\nOpenJDK Runtime Environment";
+        assert_eq!(parse_java_version(java17), Some(("17.0.2".to_owned(), 17)));
+        assert_eq!(parse_java_version("sh: java: not found"), None);
+    }
+
+    #[test]
+    fn test_parse_outputs() {
+        let df = "Filesystem 1024-blocks Used Available Capacity Mounted on\n\
+                  /dev/sda1 103081248 52428800 20971520 72% /";
+        assert_eq!(parse_free_disk_mb(df), Some(20480));
+
+        let ss = "LISTEN 0 50 0.0.0.0:30000 0.0.0.0:*\n\
+                  LISTEN 0 50 10.0.0.2:30001 0.0.0.0:*\n\
+                  LISTEN 0 50 10.0.0.1:30002 0.0.0.0:*\n\
+                  LISTEN 0 50 [::]:22 [::]:*";
+        let ports: Vec<usize> = parse_listening_ports(ss, "10.0.0.1").into_iter().collect();
+        assert_eq!(ports, [22, 30000, 30002]);
+    }
+
+    #[test]
+    fn test_find_port_clashes() {
+        let conn = |ip: &str, port| ConnectionInfo { id: 0, ip: ip.to_owned(), port };
+        let conns = vec![conn("10.0.0.1", 30000), conn("10.0.0.1", 30001),
+            conn("10.0.0.2", 30000), conn("10.0.0.1", 30000)];
+        assert_eq!(find_port_clashes(&conns), ["10.0.0.1:30000"]);
+    }
+}
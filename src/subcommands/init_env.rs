@@ -1,4 +1,6 @@
 
+use std::sync::Arc;
+
 use clap::{ArgMatches, App, SubCommand};
 use log::{info, trace};
 use colored::*;
@@ -6,62 +8,84 @@ use colored::*;
 use crate::error::{Result, BenchError};
 use crate::config::Config;
 use crate::command;
+use crate::transport::Transport;
+use crate::parallel;
+use crate::selection::HostSelection;
 
 pub fn get_sub_command<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name("init-env")
+                .arg(parallel::parallelism_arg())
+                .args(&HostSelection::args())
                 .about("initializes the environment of all machines")
 }
 
-pub fn execute(config: &Config, _: &ArgMatches) -> Result<()> {
+pub fn execute(config: &Config, transport: &Arc<dyn Transport>,
+        args: &ArgMatches) -> Result<()> {
     info!("Starts initializing the environment");
 
+    let ips = HostSelection::from_args(args).select(&config.machines)?;
+
     // Check local files
-    if !check_local_jdk(config)? {
+    let needs_jdk = ips.iter().any(|ip| config.uses_deployed_jdk(ip));
+    if needs_jdk && !check_local_jdk(config)? {
         return Err(BenchError::Message(
             format!("cannot find the JDK at {}", config.jdk.package_path)
         ));
     }
 
     // For all the nodes
-    for ip in &config.machines.all {
-        info!("Checking node '{}' ...", ip);
+    let parallelism = parallel::get_parallelism(args)?;
+    let outcomes = parallel::for_each_host(&ips, parallelism,
+        |ip| init_node(config, transport.as_ref(), ip));
 
-        // Create the working directory
-        create_working_dir(&config, ip)?;
+    parallel::print_summary(&outcomes);
+    parallel::check(&outcomes)
+}
 
-        // Check Java Runtime
-        if !check_java_runtime(&config, ip)? {
-            send_jdk(config, ip)?;
-            unpack_jdk(config, ip)?;
-            remove_jdk_package(config, ip)?;
-        }
+fn init_node(config: &Config, transport: &dyn Transport, ip: &str) -> Result<()> {
+    info!("Checking node '{}' ...", ip);
 
-        info!("Node '{}' {}", ip, "checked".green());
+    // Create the working directory
+    create_working_dir(config, transport, ip)?;
+
+    // Check Java Runtime (the local mode and the machines with their
+    // own java do not need the JDK)
+    if config.uses_deployed_jdk(ip) && !check_java_runtime(config, transport, ip)? {
+        send_jdk(config, transport, ip)?;
+        unpack_jdk(config, transport, ip)?;
+        remove_jdk_package(config, transport, ip)?;
     }
-    
+
+    info!("Node '{}' {}", ip, "checked".green());
     Ok(())
 }
 
-fn create_working_dir(config: &Config, ip: &str) -> Result<()> {
+fn create_working_dir(config: &Config, transport: &dyn Transport,
+        ip: &str) -> Result<()> {
     info!("Creating a working directory on {}", ip);
 
     for dir in ["databases", "results"].iter() {
         let cmd = format!("mkdir -p {}/{}",
-            config.system.remote_work_dir, dir);
-        command::ssh(&config.system.user_name, ip, &cmd)
+            config.work_dir(ip), dir);
+        transport.exec(ip, &cmd)
             .map(|out| trace!("mkdir: {}", out))?;
     }
     Ok(())
 }
 
-fn check_java_runtime(config: &Config, ip: &str) -> Result<bool> {
+fn check_java_runtime(config: &Config, transport: &dyn Transport,
+        ip: &str) -> Result<bool> {
     info!("Checking java runtime on {}", ip);
 
-    let cmd = format!("{}/{}/bin/java -version",
-        config.system.remote_work_dir, config.jdk.dir_name);
+    let java_bin = format!("{}/{}", config.work_dir(ip), config.java_bin(ip));
+    if !transport.exists(ip, &java_bin)? {
+        return Ok(false);
+    }
+
+    let cmd = format!("{} -version", java_bin);
 
     // Check if the java is installed
-    match command::ssh(&config.system.user_name, ip, &cmd) {
+    match transport.exec(ip, &cmd) {
         Err(BenchError::NoSuchCommandOnRemote(_, _)) => {
             Ok(false)
         },
@@ -86,28 +110,28 @@ fn check_local_jdk(config: &Config) -> Result<bool> {
 }
 
 
-fn send_jdk(config: &Config, ip: &str) -> Result<()> {
+fn send_jdk(config: &Config, transport: &dyn Transport, ip: &str) -> Result<()> {
     info!("Sending JDK to {}", ip);
 
-    command::scp_to(false, &config.system.user_name, ip, 
-            &config.jdk.package_path, &config.system.remote_work_dir)?;
-    Ok(())
+    transport.upload(false, ip, &config.jdk.package_path,
+            config.work_dir(ip))
 }
 
-fn unpack_jdk(config: &Config, ip: &str) -> Result<()> {
+fn unpack_jdk(config: &Config, transport: &dyn Transport, ip: &str) -> Result<()> {
     info!("Unpacking {} on {}", config.jdk.package_filename, ip);
     
-    let cmd = format!("tar -C {} -zxf {}/{}", config.system.remote_work_dir, 
-            config.system.remote_work_dir, config.jdk.package_filename);
-    command::ssh(&config.system.user_name, ip, &cmd)?;
+    let cmd = format!("tar -C {} -zxf {}/{}", config.work_dir(ip),
+            config.work_dir(ip), config.jdk.package_filename);
+    transport.exec(ip, &cmd)?;
     Ok(())
 }
 
-fn remove_jdk_package(config: &Config, ip: &str) -> Result<()> {
+fn remove_jdk_package(config: &Config, transport: &dyn Transport,
+        ip: &str) -> Result<()> {
     info!("Removing {} on {}", config.jdk.package_filename, ip);
     
-    let cmd = format!("rm {}/{}", config.system.remote_work_dir, 
+    let cmd = format!("rm {}/{}", config.work_dir(ip),
             config.jdk.package_filename);
-    command::ssh(&config.system.user_name, ip, &cmd)?;
+    transport.exec(ip, &cmd)?;
     Ok(())
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 
-use std::path::Path;
+use std::sync::Arc;
 
 use colored::*;
 use log::*;
@@ -7,8 +7,9 @@ use clap::{ArgMatches, Arg, App, SubCommand};
 
 use crate::error::{Result, BenchError};
 use crate::config::Config;
-use crate::parameters::ParameterList;
 use crate::connections::Action;
+use crate::transport::Transport;
+use crate::interrupt;
 
 pub fn get_sub_command<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name("load")
@@ -20,30 +21,37 @@ pub fn get_sub_command<'a, 'b>() -> App<'a, 'b> {
                     .help("The parameters of running the loading program")
                     .required(true)
                     .index(2))
+                .arg(Arg::with_name("NO VALIDATION")
+                    .long("no-validation")
+                    .help("Does not check if the properties in the parameter file exist"))
                 .about("loads the testbed using the given parameters")
 }
 
-pub fn execute(config: &Config, args: &ArgMatches) -> Result<()> {
+pub fn execute(config: &Config, transport: &Arc<dyn Transport>,
+        args: &ArgMatches) -> Result<()> {
     let db_name = args.value_of("DB NAME").unwrap();
     let param_file = args.value_of("PARAMETER FILE").unwrap();
     
+    interrupt::install_handler();
+
     info!("Preparing for loading testbed into '{}'...",
         db_name.cyan());
     info!("Using parameter file '{}'", param_file);
 
     // Read the parameter file
-    let param_list = ParameterList::from_file(Path::new(param_file))?;
+    let param_list = super::read_parameter_file(param_file,
+        !args.is_present("NO VALIDATION"))?;
 
     // The file should only produce single "Parameter"
     let param_list = param_list.to_vec();
     if param_list.len() > 1 {
-        return Err(BenchError::Message(format!(
-            "The parameter file contains more than one combination"
-        )));
+        return Err(BenchError::Message(
+            "The parameter file contains more than one combination".to_owned()
+        ));
     }
 
-    super::run(config, &param_list[0], &db_name,
-        Action::Loading, None)?;
+    super::run(config, transport, &param_list[0], db_name,
+        Action::Loading, None, false)?;
 
     // Show the final result (where is the database, the size...)
     info!("Loading testbed finished.");
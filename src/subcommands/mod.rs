@@ -4,18 +4,50 @@ pub mod load;
 pub mod benchmark;
 pub mod all_execute;
 pub mod pull;
+pub mod plan;
+pub mod cache;
+pub mod doctor;
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
 
 use log::*;
 
 use crate::error::{Result, BenchError};
-use crate::parameters::Parameter;
+use crate::parameters::{Parameter, ParameterList};
+use crate::properties::PropertiesFileMap;
 use crate::config::Config;
-use crate::command;
-use crate::connections::{Action, ConnectionInfo};
+use crate::transport::Transport;
+use crate::connections::{self, Action, ConnectionInfo, PortAllocator, Role};
 use crate::threads;
+use crate::command;
+use crate::interrupt;
+use crate::results::BenchResult;
+use crate::topology::Topology;
+
+const WARM_UP_INTERVAL: &str = "org.vanilladb.bench.BenchmarkerParameters.WARM_UP_INTERVAL";
+const BENCHMARK_INTERVAL: &str = "org.vanilladb.bench.BenchmarkerParameters.BENCHMARK_INTERVAL";
+const PROCESSES_FILENAME: &str = "processes.csv";
 
-fn run(config: &Config, parameter: &Parameter,
-        db_name: &str, action: Action, report_dir: Option<String>) -> Result<Vec<Option<u32>>> {
+// The sequencer, the servers and the clients of a job
+type Processes = (Option<ConnectionInfo>, Vec<ConnectionInfo>, Vec<ConnectionInfo>);
+
+fn run(config: &Config, transport: &Arc<dyn Transport>, parameter: &Parameter,
+        db_name: &str, action: Action, report_dir: Option<String>,
+        compress_logs: bool) -> Result<Vec<Option<BenchResult>>> {
+    let result = run_job(config, transport, parameter, db_name, action,
+        report_dir, compress_logs);
+    // Ctrl-C also stops the ssh and scp commands running at that time
+    match result {
+        Err(_) if interrupt::is_interrupted() => Err(BenchError::Interrupted),
+        result => result
+    }
+}
+
+fn run_job(config: &Config, transport: &Arc<dyn Transport>, parameter: &Parameter,
+        db_name: &str, action: Action, report_dir: Option<String>,
+        compress_logs: bool) -> Result<Vec<Option<BenchResult>>> {
     
     // Generate connection information (ip, port)
     let (sequencer, server_list, client_list) =
@@ -23,23 +55,132 @@ fn run(config: &Config, parameter: &Parameter,
     
     // Prepare the bench dir
     let vm_args = crate::preparation::prepare_bench_dir(
-        &config, parameter, &sequencer, &server_list, &client_list)?;
+        parameter, &sequencer, &server_list, &client_list)?;
 
     info!("Connecting to machines...");
 
+    let mut ips: Vec<&str> = Vec::new();
+    let machines = sequencer.iter().chain(&server_list).chain(&client_list);
+    for machine in machines {
+        if !ips.contains(&machine.ip.as_str()) {
+            ips.push(&machine.ip);
+        }
+    }
+
     info!("Killing existing benchmarker processes...");
     if let Some(ref seq) = sequencer {
-        kill_benchmarker(config, seq)?;
+        kill_benchmarker(config, transport.as_ref(), seq)?;
     }
     for server in &server_list {
-        kill_benchmarker(config, server)?;
+        kill_benchmarker(config, transport.as_ref(), server)?;
     }
     for client in &client_list {
-        kill_benchmarker(config, client)?;
+        kill_benchmarker(config, transport.as_ref(), client)?;
+    }
+
+    let jar_dir = parameter.get_autobencher_param("jar_dir")?;
+    crate::preparation::distribute_bench_dir(config, transport.as_ref(),
+        jar_dir, &ips)?;
+
+    // The clients should finish in time
+    let run_timeout = match action {
+        Action::Loading => config.timeouts.loading,
+        Action::Benchmarking => {
+            let default_properties = PropertiesFileMap::from_dir(Path::new("properties"))?;
+            Some(get_benchmark_time(&default_properties, parameter)? / 1000
+                + config.timeouts.run_slack)
+        }
+    };
+
+    // Kept for the report of the job
+    let processes = (sequencer.clone(), server_list.clone(), client_list.clone());
+
+    interrupt::check()?;
+    let job = threads::Job {
+        db_name,
+        action,
+        report_dir: report_dir.clone(),
+        vm_args: &vm_args,
+        run_timeout
+    };
+    let results = threads::run_in_threads(config, transport, &job,
+        sequencer, server_list, client_list);
+
+    // The logs are kept no matter the job succeeds or not
+    if let Some(dir) = report_dir {
+        if let Err(e) = save_job_files(config, Path::new(&dir), &processes,
+                compress_logs) {
+            warn!("Failed to save the files of the job to '{}': {}", dir, e);
+        }
     }
 
-    threads::run_in_threads(config, db_name, action, report_dir,
-        &vm_args, sequencer, server_list, client_list)
+    results
+}
+
+// Saves the properties files and the placement of the processes along
+// with the pulled logs
+fn save_job_files(config: &Config, report_dir: &Path, processes: &Processes,
+        compress_logs: bool) -> Result<()> {
+    let log_dir = report_dir.join(threads::LOG_DIR);
+    crate::preparation::copy_props(&log_dir)?;
+    save_processes(config, &log_dir, processes)?;
+    if compress_logs {
+        command::gzip_dir(log_dir.to_str().unwrap())?;
+    }
+    Ok(())
+}
+
+// Records where each process runs and how it is pinned, so that the
+// results can be read along with the placement
+fn save_processes(config: &Config, dir: &Path, processes: &Processes) -> Result<()> {
+    let (sequencer, server_list, client_list) = processes;
+    fs::create_dir_all(dir)?;
+    let mut writer = csv::Writer::from_path(dir.join(PROCESSES_FILENAME))?;
+    writer.write_record(["role", "id", "ip", "port", "pinning"])?;
+
+    let processes = sequencer.iter().map(|conn| ("sequencer", Role::Sequencer, conn))
+        .chain(server_list.iter().map(|conn| ("server", Role::Server, conn)))
+        .chain(client_list.iter().map(|conn| ("client", Role::Client, conn)));
+    for (name, role, conn) in processes {
+        let pinning = config.pinning.settings(role, conn.id).command_prefix();
+        writer.write_record([name, &conn.id.to_string(), &conn.ip,
+            &conn.port.to_string(), pinning.trim()])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// Reads the parameter file and checks its properties against
+// the default properties files unless `validate` is false
+fn read_parameter_file(path: &str, validate: bool) -> Result<ParameterList> {
+    let param_list = ParameterList::from_file(Path::new(path))?;
+    if validate {
+        let default_properties = PropertiesFileMap::from_dir(Path::new("properties"))?;
+        param_list.validate(&default_properties)?;
+    } else {
+        warn!("Skipped validating the parameter file");
+    }
+    Ok(param_list)
+}
+
+// Output: the time of warming up and benchmarking in milliseconds
+fn get_benchmark_time(default_properties: &PropertiesFileMap,
+        parameter: &Parameter) -> Result<u64> {
+    Ok(get_interval(default_properties, parameter, WARM_UP_INTERVAL)?
+        + get_interval(default_properties, parameter, BENCHMARK_INTERVAL)?)
+}
+
+// Output: the interval in milliseconds
+fn get_interval(default_properties: &PropertiesFileMap, parameter: &Parameter,
+        property: &str) -> Result<u64> {
+    let overridden = parameter.get_params().into_iter()
+        .find(|(file, prop, _)| *file == "vanillabench" && *prop == property)
+        .map(|(_, _, value)| value);
+    let value = overridden
+        .or_else(|| default_properties.get("vanillabench", property))
+        .ok_or_else(|| BenchError::Message(
+            format!("cannot find property: {}", property)))?;
+    Ok(value.trim().parse()?)
 }
 
 fn generate_connection_list(config: &Config, parameter: &Parameter, action: Action)
@@ -49,46 +190,72 @@ fn generate_connection_list(config: &Config, parameter: &Parameter, action: Acti
         .get_autobencher_param("server_count")?.parse()?;
     let server_client_ratio: f64 = parameter
         .get_autobencher_param("server_client_ratio")?.parse()?;
+    let client_count = (server_count as f64 * server_client_ratio) as usize;
+
+    // The processes are placed round-robin if there is no topology
+    if let Ok(path) = parameter.get_autobencher_param("topology") {
+        let topology = Topology::from_file(Path::new(path))?;
+        return topology.place(&config.machines, &config.ports, server_count,
+            client_count, action);
+    }
+
     let max_server_per_machine: usize = parameter
         .get_autobencher_param("max_server_per_machine")?.parse()?;
     let max_client_per_machine: usize = parameter
         .get_autobencher_param("max_client_per_machine")?.parse()?;
-    
-    let client_count = (server_count as f64 * server_client_ratio) as usize;
 
-    let sequencer = config.machines.sequencer.clone().map(|seq_ip| ConnectionInfo {
-        id: server_count,
-        ip: seq_ip,
-        port: 30000
-    });
+    // The processes sharing a machine get different ports
+    let mut allocator = PortAllocator::new();
+    let sequencer = match config.machines.sequencer.clone() {
+        Some(seq_ip) => Some(ConnectionInfo {
+            id: server_count,
+            port: allocator.allocate(&seq_ip, &config.ports.sequencer)?,
+            ip: seq_ip
+        }),
+        None => None
+    };
     let server_list = ConnectionInfo::generate_connection_list(
         &config.machines.servers,
         server_count,
-        max_server_per_machine
+        max_server_per_machine,
+        &config.ports.servers,
+        &mut allocator
     )?;
     let client_list = if let Action::Loading = action {
         ConnectionInfo::generate_connection_list(
             &config.machines.clients,
             1,
-            max_client_per_machine
+            max_client_per_machine,
+            &config.ports.clients,
+            &mut allocator
         )?
     } else {
         ConnectionInfo::generate_connection_list(
             &config.machines.clients,
             client_count,
-            max_client_per_machine
+            max_client_per_machine,
+            &config.ports.clients,
+            &mut allocator
         )?
     };
 
     Ok((sequencer, server_list, client_list))
 }
 
-fn kill_benchmarker(config: &Config, machine: &ConnectionInfo) -> Result<()> {
-    let result = command::ssh(
-        &config.system.user_name,
-        &machine.ip,
-        "pkill -f benchmarker"
-    );
+fn kill_benchmarker(config: &Config, transport: &dyn Transport,
+        machine: &ConnectionInfo) -> Result<()> {
+    let pattern = if config.local.enabled {
+        // Other programs of the user run on localhost as well, so only
+        // the jars in the working directory of the machine are matched
+        let root_dir = config.local_root_dir();
+        fs::create_dir_all(&root_dir)?;
+        let bench_dir = fs::canonicalize(root_dir)?.join(&machine.ip)
+            .join(config.work_dir(&machine.ip)).join("benchmarker");
+        connections::command_pattern(&format!("{}/", bench_dir.display()))
+    } else {
+        connections::command_pattern("benchmarker")
+    };
+    let result = transport.exec(&machine.ip, &format!("pkill -f {}", pattern));
     match result {
         Err(BenchError::CommandFailedOnRemote(_, _, 1, _)) =>
                 debug!("No existing process is found on '{}'", machine.ip),
@@ -97,3 +264,34 @@ fn kill_benchmarker(config: &Config, machine: &ConnectionInfo) -> Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+    use crate::test_utils::{test_config, temp_path};
+
+    #[test]
+    fn test_kill_benchmarker() {
+        let mock = MockTransport::new(|_, _| Ok("".to_owned()));
+        let mut config = test_config();
+        let server = ConnectionInfo { id: 0, ip: "10.0.0.2".to_owned(), port: 30000 };
+        kill_benchmarker(&config, &mock, &server).unwrap();
+
+        // Only the jars under the directory of the machine are matched
+        let root_dir = temp_path("kill");
+        config.local.root_dir = Some(root_dir.display().to_string());
+        config.use_local_mode();
+        let server = ConnectionInfo { id: 0, ip: "127.0.1.1".to_owned(), port: 30000 };
+        kill_benchmarker(&config, &mock, &server).unwrap();
+
+        let bench_dir = fs::canonicalize(&root_dir).unwrap()
+            .join("127.0.1.1/work/benchmarker");
+        assert_eq!(mock.records(), [
+            "exec 10.0.0.2 pkill -f '[b]enchmarker'".to_owned(),
+            format!("exec 127.0.1.1 pkill -f '[/]{}/'",
+                &bench_dir.display().to_string()[1 ..])
+        ]);
+        fs::remove_dir_all(&root_dir).unwrap();
+    }
+}
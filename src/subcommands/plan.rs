@@ -0,0 +1,203 @@
+use std::path::Path;
+use std::collections::BTreeMap;
+
+use colored::*;
+use clap::{ArgMatches, Arg, App, SubCommand};
+use serde::Serialize;
+
+use crate::error::Result;
+use crate::config::Config;
+use crate::parameters::Parameter;
+use crate::properties::PropertiesFileMap;
+use crate::connections::{Action, ConnectionInfo};
+
+#[derive(Serialize, Debug)]
+struct Plan {
+    jobs: Vec<JobPlan>,
+    estimated_seconds: u64
+}
+
+#[derive(Serialize, Debug)]
+struct JobPlan {
+    job_id: usize,
+    // filename.property => value
+    properties: BTreeMap<String, String>,
+    sequencer: Option<String>,
+    servers: Vec<String>,
+    clients: Vec<String>,
+    repetitions: usize,
+    // for all the repetitions
+    estimated_seconds: u64
+}
+
+pub fn get_sub_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("plan")
+                .arg(Arg::with_name("PARAMETER FILE")
+                    .help("The parameters of running the benchmarks")
+                    .required(true)
+                    .index(1))
+                .arg(Arg::with_name("REPETITIONS")
+                    .long("repetitions")
+                    .short("n")
+                    .value_name("N")
+                    .takes_value(true)
+                    .help("Runs each job N times. Overrides 'repetitions' in the parameter file."))
+                .arg(Arg::with_name("NO VALIDATION")
+                    .long("no-validation")
+                    .help("Does not check if the properties in the parameter file exist"))
+                .arg(Arg::with_name("JSON")
+                    .long("json")
+                    .help("Prints the plan in JSON"))
+                .about("shows the jobs and the placement of processes without running them")
+}
+
+pub fn execute(config: &Config, args: &ArgMatches) -> Result<()> {
+    let param_file = args.value_of("PARAMETER FILE").unwrap();
+    let repetitions_arg: Option<usize> = match args.value_of("REPETITIONS") {
+        Some(n) => Some(n.parse()?),
+        None => None
+    };
+
+    let param_list = super::read_parameter_file(param_file,
+        !args.is_present("NO VALIDATION"))?;
+    let param_list = param_list.to_vec();
+    let default_properties = PropertiesFileMap::from_dir(Path::new("properties"))?;
+
+    let plan = make_plan(config, &default_properties, &param_list,
+        repetitions_arg)?;
+
+    if args.is_present("JSON") {
+        println!("{}", serde_json::to_string_pretty(&plan)?);
+    } else {
+        print_plan(&plan);
+    }
+
+    Ok(())
+}
+
+fn make_plan(config: &Config, default_properties: &PropertiesFileMap,
+        param_list: &[Parameter], repetitions_arg: Option<usize>) -> Result<Plan> {
+    let mut jobs = Vec::new();
+    for (job_id, parameter) in param_list.iter().enumerate() {
+        jobs.push(plan_job(config, default_properties, job_id, parameter,
+            repetitions_arg)?);
+    }
+    Ok(Plan {
+        estimated_seconds: jobs.iter().map(|j| j.estimated_seconds).sum(),
+        jobs
+    })
+}
+
+fn plan_job(config: &Config, default_properties: &PropertiesFileMap,
+        job_id: usize, parameter: &Parameter, repetitions_arg: Option<usize>)
+        -> Result<JobPlan> {
+    let (sequencer, server_list, client_list) = super::generate_connection_list(
+        config, parameter, Action::Benchmarking)?;
+    let repetitions = super::benchmark::get_repetitions(parameter, repetitions_arg)?;
+
+    let properties = parameter.get_params().into_iter()
+        .map(|(file, prop, value)| (format!("{}.{}", file, prop), value.to_owned()))
+        .collect();
+
+    let interval = super::get_benchmark_time(default_properties, parameter)?;
+
+    Ok(JobPlan {
+        job_id,
+        properties,
+        sequencer: sequencer.as_ref().map(address),
+        servers: server_list.iter().map(address).collect(),
+        clients: client_list.iter().map(address).collect(),
+        repetitions,
+        estimated_seconds: interval * repetitions as u64 / 1000
+    })
+}
+
+fn address(conn: &ConnectionInfo) -> String {
+    format!("{}:{}", conn.ip, conn.port)
+}
+
+fn print_plan(plan: &Plan) {
+    for job in &plan.jobs {
+        println!("{} (estimated {}, {} trial(s))",
+            format!("Job {}", job.job_id).cyan(),
+            format_duration(job.estimated_seconds), job.repetitions);
+        for (prop, value) in &job.properties {
+            println!("  {} = {}", prop, value);
+        }
+        if let Some(seq) = &job.sequencer {
+            println!("  sequencer: {}", seq);
+        }
+        for (id, server) in job.servers.iter().enumerate() {
+            println!("  server {}: {}", id, server);
+        }
+        for (id, client) in job.clients.iter().enumerate() {
+            println!("  client {}: {}", id, client);
+        }
+    }
+    println!("{} jobs in total. Estimated time: {}",
+        plan.jobs.len().to_string().cyan(),
+        format_duration(plan.estimated_seconds).cyan());
+}
+
+fn format_duration(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}h {}m {}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{config_with_machines, parse_parameters};
+
+    fn test_config() -> Config {
+        config_with_machines(r#"
+            sequencer = "10.0.0.1"
+            servers = ["10.0.0.2", "10.0.0.3"]
+            clients = ["10.0.0.4"]
+        "#).unwrap()
+    }
+
+    #[test]
+    fn test_make_plan() {
+        let param_list = parse_parameters(r#"
+            [auto_bencher]
+            server_count = "1 3"
+            server_client_ratio = "1"
+            max_server_per_machine = "2"
+            max_client_per_machine = "3"
+            repetitions = "2"
+            [vanillabench]
+            "org.vanilladb.bench.BenchmarkerParameters.BENCHMARK_INTERVAL" = "30000"
+        "#).unwrap();
+        let default_properties = PropertiesFileMap::from_dir(Path::new("properties"))
+            .unwrap();
+        let plan = make_plan(&test_config(), &default_properties, &param_list.to_vec(),
+            None).unwrap();
+
+        // Warming up for 60 seconds and benchmarking for 30 seconds twice
+        let job = &plan.jobs[1];
+        assert_eq!(job.repetitions, 2);
+        assert_eq!(job.estimated_seconds, 180);
+        assert_eq!(job.sequencer.as_deref(), Some("10.0.0.1:30000"));
+        assert_eq!(job.servers, ["10.0.0.2:30000", "10.0.0.3:30000", "10.0.0.2:30001"]);
+        assert_eq!(job.clients, ["10.0.0.4:30000", "10.0.0.4:30001", "10.0.0.4:30002"]);
+        assert_eq!(plan.estimated_seconds, 360);
+
+        let plan = make_plan(&test_config(), &default_properties, &param_list.to_vec(),
+            Some(1)).unwrap();
+        assert_eq!(plan.estimated_seconds, 180);
+
+        let json = serde_json::to_value(&plan).unwrap();
+        assert_eq!(json["estimated_seconds"], 180);
+        assert_eq!(json["jobs"][0]["job_id"], 0);
+        assert_eq!(json["jobs"][0]["properties"]["auto_bencher.server_count"], "1");
+        assert_eq!(json["jobs"][0]["servers"][0], "10.0.0.2:30000");
+        assert_eq!(json["jobs"][1]["sequencer"], "10.0.0.1:30000");
+    }
+}
@@ -1,12 +1,15 @@
 
 use std::fs;
+use std::sync::Arc;
 
 use log::*;
 use clap::{ArgMatches, Arg, App, SubCommand};
 
 use crate::error::Result;
 use crate::config::Config;
-use crate::command;
+use crate::transport::Transport;
+use crate::parallel;
+use crate::selection::HostSelection;
 
 pub fn get_sub_command<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name("pull")
@@ -21,35 +24,48 @@ pub fn get_sub_command<'a, 'b>() -> App<'a, 'b> {
                 .arg(Arg::with_name("IGNORE ERROR")
                     .long("ignore-error")
                     .short("i")
-                    .help("If there is an error happens in a job, do not stop and proceed to the next job."))
+                    .help("Does not fail if the files cannot be pulled from some of the machines"))
+                .arg(parallel::parallelism_arg())
+                .args(&HostSelection::args())
                 .about("pulls the files whose file name matching the given pattern")
 }
 
-pub fn execute(config: &Config, args: &ArgMatches) -> Result<()> {
+pub fn execute(config: &Config, transport: &Arc<dyn Transport>,
+        args: &ArgMatches) -> Result<()> {
     let pattern = args.value_of("PATTERN").unwrap();
     let is_separated = args.is_present("SEPARATE");
     let ignore_error = args.is_present("IGNORE ERROR");
-    let remote_path = format!("{}/{}", &config.system.remote_work_dir, pattern);
 
     let local_dir = "pulls";
-    fs::create_dir_all(&local_dir)?;
-
-    for ip in &config.machines.all {
-        info!("Pulling files from {}...", &ip);
-        let result = if is_separated {
-            let local_path = format!("{}/{}", local_dir, ip);
-            fs::create_dir_all(&local_path)?;
-            command::scp_from(false, &config.system.user_name, &ip, &remote_path, &local_path)
-        } else {
-            command::scp_from(false, &config.system.user_name, &ip, &remote_path, &local_dir)
-        };
+    fs::create_dir_all(local_dir)?;
 
-        if let Err(e) = result {
-            if ignore_error {
-                warn!("{}", e);
+    // The files of the same name from different machines would be
+    // written to the same place at the same time
+    let parallelism = if is_separated {
+        parallel::get_parallelism(args)?
+    } else {
+        1
+    };
+    let ips = HostSelection::from_args(args).select(&config.machines)?;
+    let outcomes = parallel::for_each_host(&ips, parallelism,
+        |ip| {
+            info!("Pulling files from {}...", ip);
+            let remote_path = format!("{}/{}", config.work_dir(ip), pattern);
+            if is_separated {
+                let local_path = format!("{}/{}", local_dir, ip);
+                fs::create_dir_all(&local_path)?;
+                transport.download(false, ip, &remote_path, &local_path)
             } else {
-                return Err(e);
+                transport.download(false, ip, &remote_path, local_dir)
             }
+        });
+
+    parallel::print_summary(&outcomes);
+    if let Err(e) = parallel::check(&outcomes) {
+        if ignore_error {
+            warn!("{}", e);
+        } else {
+            return Err(e);
         }
     }
 
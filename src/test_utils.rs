@@ -0,0 +1,58 @@
+// Fixtures shared by the tests
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::parameters::ParameterList;
+
+static NEXT_TEMP_ID: AtomicUsize = AtomicUsize::new(0);
+
+// A config with the given `[machines]` section
+pub fn config_with_machines(machines: &str)
+        -> std::result::Result<Config, toml::de::Error> {
+    toml::from_str(&format!(r#"
+        [system]
+        user_name = "tester"
+        remote_work_dir = "work"
+
+        [jdk]
+        use_custom_jdk = true
+        dir_name = "jdk"
+        package_path = "package/jdk.tar.gz"
+
+        [jdk.vmargs]
+        sequencer = ""
+        servers = ""
+        clients = ""
+
+        [machines]
+        {}
+    "#, machines))
+}
+
+// A sequencer, a server and a client on their own machines
+pub fn test_config() -> Config {
+    config_with_machines(r#"
+        sequencer = "10.0.0.1"
+        servers = ["10.0.0.2"]
+        clients = ["10.0.0.3"]
+    "#).unwrap()
+}
+
+// A path under the temporary directory. The tests run in parallel,
+// so each call gets its own path.
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("auto-bencher-{}-{}-{}",
+        std::process::id(), NEXT_TEMP_ID.fetch_add(1, Ordering::SeqCst), name))
+}
+
+// Reads the parameter file with the content
+pub fn parse_parameters(toml_str: &str) -> Result<ParameterList> {
+    let path = temp_path("params.toml");
+    std::fs::write(&path, toml_str).unwrap();
+    let list = ParameterList::from_file(&path);
+    std::fs::remove_file(&path).unwrap();
+    list
+}
@@ -1,40 +1,36 @@
-
-use std::sync::{Arc, Barrier};
+use std::sync::{Arc, Barrier, RwLock};
 use std::sync::mpsc::Sender;
 use std::thread::{self, JoinHandle};
-use std::time::Duration;
 
 use log::*;
 
-use crate::error::{Result};
-use crate::config::Config;
-use crate::connections::{Client, Action, ConnectionInfo};
-use super::{ThreadResult, CHECKING_INTERVAL};
+use crate::error::Result;
+use crate::config::Timeouts;
+use crate::results::BenchResult;
+use crate::connections::{Client, Action};
+use super::{ThreadResult, StopSign};
 
 pub fn create_client_thread(barrier: Arc<Barrier>,
-        config: Config, conn_info: ConnectionInfo,
-        vm_args: String, result_ch: Sender<ThreadResult>,
-        action: Action, report_dir: Option<String>)
+        stop_sign: Arc<RwLock<StopSign>>, client: Arc<Client>,
+        result_ch: Sender<ThreadResult>, report_dir: Option<String>,
+        timeouts: Timeouts, run_timeout: Option<u64>)
         -> JoinHandle<()> {
     thread::spawn(move || {
-        let client = Client::new(config, conn_info, vm_args);
-        let result = match execute_client_thread(&client, barrier, action, report_dir) {
-            Err(e) => {
-                error!("Client {} (on {}) occurs an error: {}",
-                    client.id(), client.ip(), e);
-                ThreadResult::Failed
-            },
-            Ok(th) => ThreadResult::ClientSucceed(th)
-        };
+        let result = execute_client_thread(&client, barrier, &stop_sign,
+                report_dir, &timeouts, run_timeout)
+            .map(ThreadResult::ClientSucceed);
         debug!("Client {} finished.", client.id());
-        result_ch.send(result).unwrap();
+        super::send_result(&result_ch, result,
+            &format!("Client {}", client.id()), client.ip(),
+            || client.dump_threads());
     })
 }
 
 fn execute_client_thread(client: &Client, barrier: Arc<Barrier>,
-        action: Action, report_dir: Option<String>) -> Result<Option<u32>> {
+        stop_sign: &RwLock<StopSign>, report_dir: Option<String>,
+        timeouts: &Timeouts, run_timeout: Option<u64>)
+        -> Result<Option<BenchResult>> {
     client.clean_previous_results()?;
-    client.send_bench_dir()?;
 
     // Wait for the server ready
     barrier.wait(); // prepared
@@ -45,7 +41,11 @@ fn execute_client_thread(client: &Client, barrier: Arc<Barrier>,
         info!("Starting clients...");
     }
 
-    client.start(action)?;
+    client.start()?;
+    // A short run may have finished before the check
+    super::wait_for(|| Ok(client.is_running()? || client.check_for_finished()?),
+        Some(timeouts.startup),
+        &format!("client {} did not start", client.id()), stop_sign)?;
 
     // Wait for client started
     barrier.wait(); // client started
@@ -54,17 +54,16 @@ fn execute_client_thread(client: &Client, barrier: Arc<Barrier>,
         info!("All clients are running. Waiting for finishing...");
     }
 
-    while !client.check_for_finished(action)? {
-        thread::sleep(Duration::from_secs(CHECKING_INTERVAL));
-    }
+    super::wait_for(|| client.check_for_finished(), run_timeout,
+        &format!("client {} did not finish", client.id()), stop_sign)?;
 
-    if let Action::Benchmarking = action {
+    if let Action::Benchmarking = client.action() {
         client.pull_csv(&report_dir.unwrap())?;
-        let throughput = client.get_total_throughput()?;
+        let result = client.get_result()?;
         debug!("The total throughput of client {} is {}",
-            client.id(), throughput);
-        Ok(Some(throughput))
+            client.id(), result.total.committed);
+        Ok(Some(result))
     } else {
         Ok(None)
     }
-}
\ No newline at end of file
+}
@@ -2,30 +2,62 @@
 mod server;
 mod client;
 
+use std::fs;
+use std::path::Path;
 use std::sync::{Arc, Barrier, RwLock};
-use std::sync::mpsc::{self, Sender, Receiver};
+use std::sync::mpsc::{self, Sender, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use log::*;
 
 use crate::error::{Result, BenchError};
 use crate::config::Config;
-use crate::connections::{Action, ConnectionInfo};
+use crate::transport::Transport;
+use crate::results::BenchResult;
+use crate::interrupt;
+use crate::connections::{Action, ConnectionInfo, Server, Client};
 
 const CHECKING_INTERVAL: u64 = 1;
+pub const LOG_DIR: &str = "logs";
 
 pub enum ThreadResult {
     ServerSucceed,
-    ClientSucceed(Option<u32>),
+    ClientSucceed(Option<BenchResult>),
+    // (what did not happen in time)
+    TimedOut(String),
+    // The user pressed Ctrl-C
+    Interrupted,
     Failed
 }
 
-pub fn run_in_threads(config: &Config, db_name: &str,
-        action: Action, report_dir: Option<String>,
-        vm_args: &str,
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopSign {
+    Running,
+    // The clients finished
+    Finished,
+    // A process failed or the user interrupted
+    Aborted
+}
+
+// The settings of a job shared by all its processes
+pub struct Job<'a> {
+    pub db_name: &'a str,
+    pub action: Action,
+    pub report_dir: Option<String>,
+    pub vm_args: &'a str,
+    // The time limit of the clients in seconds
+    pub run_timeout: Option<u64>
+}
+
+pub fn run_in_threads(config: &Config, transport: &Arc<dyn Transport>,
+        job: &Job,
         sequencer: Option<ConnectionInfo>,
         server_list: Vec<ConnectionInfo>,
         client_list: Vec<ConnectionInfo>)
-        -> Result<Vec<Option<u32>>> {
+        -> Result<Vec<Option<BenchResult>>> {
+    let Job { db_name, action, ref report_dir, vm_args, run_timeout } = *job;
+
     // Use a mspc channel to collect results
     let (tx, rx): (Sender<ThreadResult>, Receiver<ThreadResult>)
         = mpsc::channel();
@@ -40,85 +72,112 @@ pub fn run_in_threads(config: &Config, db_name: &str,
 
     // Add other vm arguments for servers
     let mut server_vm_args = vm_args.to_owned();
-    server_vm_args.push_str(" ");
+    server_vm_args.push(' ');
     server_vm_args.push_str(&config.jdk.vmargs.servers);
 
     // Create server connections
-    let stop_sign = Arc::new(RwLock::new(false));
+    let mut servers = Vec::new();
     for server_conn in &server_list {
-        let handle = server::create_server_thread(
-            barrier.clone(),
-            stop_sign.clone(),
-            config.clone(),
-            server_conn.clone(),
-            db_name.to_owned(), server_vm_args.clone(),
-            false,
-            tx.clone(),
-            action
-        );
-        threads.push(handle);
+        servers.push(Arc::new(Server::new(config.clone(), transport.clone(),
+            server_conn.clone(), db_name.to_owned(), server_vm_args.clone(),
+            false, action)?));
     }
 
     // Create sequencer connection
     if let Some(seq_conn) = sequencer {
         // Add other vm arguments
         let mut seq_vm_args = vm_args.to_owned();
-        seq_vm_args.push_str(" ");
+        seq_vm_args.push(' ');
         seq_vm_args.push_str(&config.jdk.vmargs.sequencer);
 
+        servers.push(Arc::new(Server::new(config.clone(), transport.clone(),
+            seq_conn, db_name.to_owned(), seq_vm_args, true, action)?));
+    }
+
+    let stop_sign = Arc::new(RwLock::new(StopSign::Running));
+    for server in &servers {
         let handle = server::create_server_thread(
             barrier.clone(),
             stop_sign.clone(),
-            config.clone(),
-            seq_conn.clone(),
-            db_name.to_owned(), seq_vm_args,
-            true,
+            server.clone(),
             tx.clone(),
-            action
+            action,
+            config.timeouts.clone()
         );
         threads.push(handle);
     }
 
     // Add other vm arguments for clients
     let mut client_vm_args = vm_args.to_owned();
-    client_vm_args.push_str(" ");
+    client_vm_args.push(' ');
     client_vm_args.push_str(&config.jdk.vmargs.clients);
 
     // Create client connections
+    let mut clients = Vec::new();
     for client_conn in &client_list {
+        let client = Arc::new(Client::new(config.clone(), transport.clone(),
+            client_conn.clone(), client_vm_args.clone(), action)?);
         let handle = client::create_client_thread(
             barrier.clone(),
-            config.clone(),
-            client_conn.clone(),
-            client_vm_args.clone(),
+            stop_sign.clone(),
+            client.clone(),
             tx.clone(),
-            action,
-            report_dir.clone()
+            report_dir.clone(),
+            config.timeouts.clone(),
+            run_timeout
         );
         threads.push(handle);
+        clients.push(client);
     }
 
     // Check if there is any error
-    let mut client_results: Vec<Option<u32>> = Vec::new();
-    for _ in 0 .. threads.len() {
-        match rx.recv().unwrap() {
-            ThreadResult::ClientSucceed(th) => {
-                client_results.push(th);
+    let mut client_results: Vec<Option<BenchResult>> = Vec::new();
+    let mut finished_count = 0;
+    while finished_count < threads.len() {
+        if interrupt::is_interrupted() {
+            warn!("Interrupted. Stopping all the processes...");
+            *stop_sign.write()? = StopSign::Aborted;
+            tear_down(&servers, &clients);
+            collect_logs(&servers, &clients, report_dir.as_deref())?;
+            return Err(BenchError::Interrupted);
+        }
+
+        let result = match rx.recv_timeout(Duration::from_secs(CHECKING_INTERVAL)) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => unreachable!()
+        };
+        finished_count += 1;
+
+        let error = match result {
+            ThreadResult::ClientSucceed(result) => {
+                client_results.push(result);
                 if client_results.len() >= client_list.len() {
                     info!("All clients finished properly. Stopping server threads...");
 
                     // Notify the servers to finish
                     let mut stop = stop_sign.write().unwrap();
-                    *stop = true;
+                    *stop = StopSign::Finished;
                 }
+                continue;
             },
-            ThreadResult::Failed => {
-                return Err(BenchError::Message(
-                    "A thread exits with an error".to_owned()
-                ));
-            },
-            _ => {}
-        }
+            ThreadResult::ServerSucceed => continue,
+            ThreadResult::TimedOut(message) => BenchError::Timeout(message),
+            ThreadResult::Interrupted => BenchError::Interrupted,
+            // A thread may fail because its process is stopped by Ctrl-C
+            ThreadResult::Failed if interrupt::is_interrupted() =>
+                BenchError::Interrupted,
+            ThreadResult::Failed => BenchError::Message(
+                "A thread exits with an error".to_owned()
+            )
+        };
+
+        // Stop the other threads and their processes
+        warn!("Stopping all the processes...");
+        *stop_sign.write()? = StopSign::Aborted;
+        tear_down(&servers, &clients);
+        collect_logs(&servers, &clients, report_dir.as_deref())?;
+        return Err(error);
     }
 
     // Wait for the threads finish
@@ -128,5 +187,240 @@ pub fn run_in_threads(config: &Config, db_name: &str,
 
     info!("All threads exits properly.");
 
+    stop_watching_logs(&servers, &clients);
+
+    collect_logs(&servers, &clients, report_dir.as_deref())?;
+
     Ok(client_results)
-}
\ No newline at end of file
+}
+
+// Reports the result of a worker thread to the main thread. The threads
+// of a timed out process are dumped to its log for debugging.
+fn send_result<F>(result_ch: &Sender<ThreadResult>, result: Result<ThreadResult>,
+        name: &str, ip: &str, dump_threads: F)
+        where F: FnOnce() -> Result<()> {
+    let result = match result {
+        Err(BenchError::Timeout(message)) => {
+            error!("{} (on {}) timed out: {}", name, ip, message);
+            if let Err(e) = dump_threads() {
+                warn!("Failed to dump the threads of {} (on {}): {}", name, ip, e);
+            }
+            // Give the JVM some time to write the dump
+            thread::sleep(Duration::from_secs(CHECKING_INTERVAL));
+            ThreadResult::TimedOut(message)
+        },
+        Err(BenchError::Interrupted) => ThreadResult::Interrupted,
+        Err(e) => {
+            error!("{} (on {}) occurs an error: {}", name, ip, e);
+            ThreadResult::Failed
+        },
+        Ok(result) => result
+    };
+    // The main thread does not wait for the others after a failure
+    let _ = result_ch.send(result);
+}
+
+// Polls the condition until it holds. Fails if it does not hold
+// in `timeout` seconds or the job is aborted.
+fn wait_for<F>(mut condition: F, timeout: Option<u64>, what: &str,
+        stop_sign: &RwLock<StopSign>) -> Result<()>
+        where F: FnMut() -> Result<bool> {
+    let start = Instant::now();
+    while !condition()? {
+        check_aborted(stop_sign)?;
+        if let Some(timeout) = timeout {
+            if start.elapsed() > Duration::from_secs(timeout) {
+                return Err(BenchError::Timeout(
+                    format!("{} in {} seconds", what, timeout)));
+            }
+        }
+        thread::sleep(Duration::from_secs(CHECKING_INTERVAL));
+    }
+    Ok(())
+}
+
+fn check_aborted(stop_sign: &RwLock<StopSign>) -> Result<()> {
+    interrupt::check()?;
+    if *stop_sign.read()? == StopSign::Aborted {
+        return Err(BenchError::Message("aborted".to_owned()));
+    }
+    Ok(())
+}
+
+// Kills the processes started by this run.
+// The worker threads may be blocked, so they are not waited for.
+fn tear_down(servers: &[Arc<Server>], clients: &[Arc<Client>]) {
+    stop_watching_logs(servers, clients);
+    for client in clients {
+        if let Err(e) = client.kill() {
+            warn!("Failed to kill client {}: {}", client.id(), e);
+        }
+    }
+    for server in servers {
+        if let Err(e) = server.kill() {
+            warn!("Failed to kill server {}: {}", server.id(), e);
+        }
+    }
+}
+
+fn stop_watching_logs(servers: &[Arc<Server>], clients: &[Arc<Client>]) {
+    for client in clients {
+        client.stop_watching_log();
+    }
+    for server in servers {
+        server.stop_watching_log();
+    }
+}
+
+// Pulls the logs of all the processes to `[report dir]/logs`
+fn collect_logs(servers: &[Arc<Server>], clients: &[Arc<Client>],
+        report_dir: Option<&str>) -> Result<()> {
+    let log_dir = match report_dir {
+        Some(dir) => Path::new(dir).join(LOG_DIR),
+        None => return Ok(())
+    };
+    fs::create_dir_all(&log_dir)?;
+    let log_dir = log_dir.to_str().unwrap();
+
+    info!("Pulling the logs to '{}'...", log_dir);
+    for client in clients {
+        if let Err(e) = client.pull_log(log_dir) {
+            warn!("Failed to pull the log of client {}: {}", client.id(), e);
+        }
+    }
+    for server in servers {
+        if let Err(e) = server.pull_log(log_dir) {
+            warn!("Failed to pull the log of server {}: {}", server.id(), e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::transport::mock::MockTransport;
+    use crate::test_utils::{test_config, temp_path};
+
+    // The interrupted flag is shared by all the runs
+    static RUN_LOCK: Mutex<()> = Mutex::new(());
+
+    fn lock_runs() -> std::sync::MutexGuard<'static, ()> {
+        RUN_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn job(report_dir: &str) -> Job<'static> {
+        Job {
+            db_name: "db",
+            action: Action::Benchmarking,
+            report_dir: Some(report_dir.to_owned()),
+            vm_args: "",
+            run_timeout: Some(60)
+        }
+    }
+
+    fn conn(id: usize, ip: &str) -> ConnectionInfo {
+        ConnectionInfo {
+            id,
+            ip: ip.to_owned(),
+            port: 30000
+        }
+    }
+
+    #[test]
+    fn test_benchmarking_flow() {
+        let _lock = lock_runs();
+        let mock = Arc::new(MockTransport::new(|_, cmd| {
+            if cmd.starts_with("tail") {
+                Ok("ElaSQL server ready\nbenchmark process finished.\n".to_owned())
+            } else if cmd.starts_with("cat") {
+                Ok("TOTAL - committed: 100, aborted: 3, avg latency: 10 ms"
+                    .to_owned())
+            } else if cmd.starts_with("ls") {
+                Ok("result-0.csv".to_owned())
+            } else {
+                Ok("".to_owned())
+            }
+        }));
+        let transport: Arc<dyn Transport> = mock.clone();
+        let report_dir = temp_path("flow").display().to_string();
+
+        let results = run_in_threads(&test_config(), &transport,
+            &job(&report_dir), Some(conn(1, "10.0.0.1")),
+            vec![conn(0, "10.0.0.2")], vec![conn(0, "10.0.0.3")]).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].as_ref().unwrap().total.committed, 100);
+
+        let records = mock.records();
+        assert!(records.contains(&format!(
+            "download 10.0.0.3 work/results/result-0.csv {}", report_dir)));
+        for log in &["server-seq.log", "server-0.log", "client-0.log"] {
+            assert!(records.iter().any(|r| r.starts_with("download")
+                && r.ends_with(&format!("work/{} {}/logs", log, report_dir))));
+        }
+        fs::remove_dir_all(&report_dir).unwrap();
+    }
+
+    #[test]
+    fn test_ready_timeout() {
+        let _lock = lock_runs();
+        // Server 0 never becomes ready
+        let mock = Arc::new(MockTransport::new(|ip, cmd| {
+            if cmd.starts_with("tail") && ip != "10.0.0.2" {
+                Ok("ElaSQL server ready\n".to_owned())
+            } else {
+                Ok("".to_owned())
+            }
+        }));
+        let transport: Arc<dyn Transport> = mock.clone();
+        let mut config = test_config();
+        config.timeouts.ready = 1;
+        let report_dir = temp_path("timeout").display().to_string();
+
+        let result = run_in_threads(&config, &transport, &job(&report_dir),
+            Some(conn(1, "10.0.0.1")), vec![conn(0, "10.0.0.2")],
+            vec![conn(0, "10.0.0.3")]);
+
+        match result {
+            Err(BenchError::Timeout(message)) =>
+                assert_eq!(message, "server 0 was not ready in 1 seconds"),
+            _ => panic!("the job should time out")
+        }
+        let records = mock.records();
+        assert!(records.iter().any(|r| r.starts_with("exec 10.0.0.2 pkill -QUIT")));
+        assert!(records.iter().any(|r| r.starts_with("exec 10.0.0.2 pkill -TERM")));
+        assert!(records.contains(&format!(
+            "download 10.0.0.2 work/server-0.log {}/logs", report_dir)));
+        fs::remove_dir_all(&report_dir).unwrap();
+    }
+    #[test]
+    fn test_interrupted() {
+        let _lock = lock_runs();
+        // Ctrl-C is pressed once server 0 starts, which never becomes ready
+        let mock = Arc::new(MockTransport::new(|ip, cmd| {
+            if ip == "10.0.0.2" && cmd.contains("-jar") {
+                interrupt::set_interrupted(true);
+            }
+            Ok("".to_owned())
+        }));
+        let transport: Arc<dyn Transport> = mock.clone();
+        let report_dir = temp_path("interrupted").display().to_string();
+
+        let result = run_in_threads(&test_config(), &transport, &job(&report_dir),
+            Some(conn(1, "10.0.0.1")), vec![conn(0, "10.0.0.2")],
+            vec![conn(0, "10.0.0.3")]);
+        interrupt::set_interrupted(false);
+
+        match result {
+            Err(BenchError::Interrupted) => {},
+            Err(e) => panic!("the job should be interrupted instead of: {}", e),
+            Ok(_) => panic!("the job should be interrupted")
+        }
+        let records = mock.records();
+        assert!(records.iter().any(|r| r.starts_with("exec 10.0.0.2 pkill -TERM")));
+        fs::remove_dir_all(&report_dir).unwrap();
+    }
+}
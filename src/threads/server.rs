@@ -1,4 +1,3 @@
-
 use std::sync::{Arc, Barrier, RwLock};
 use std::sync::mpsc::Sender;
 use std::thread::{self, JoinHandle};
@@ -7,46 +6,37 @@ use std::time::Duration;
 use log::*;
 
 use crate::error::Result;
-use crate::config::Config;
-use crate::connections::{Server, Action, ConnectionInfo};
-use super::{ThreadResult, CHECKING_INTERVAL};
+use crate::config::Timeouts;
+use crate::connections::{Server, Action};
+use super::{ThreadResult, StopSign, CHECKING_INTERVAL};
 
 pub fn create_server_thread(barrier: Arc<Barrier>,
-        stop_sign: Arc<RwLock<bool>>, config: Config,
-        conn_info: ConnectionInfo, db_name: String,
-        vm_args: String, is_sequencer: bool,
-        result_ch: Sender<ThreadResult>, action: Action)
+        stop_sign: Arc<RwLock<StopSign>>, server: Arc<Server>,
+        result_ch: Sender<ThreadResult>, action: Action, timeouts: Timeouts)
         -> JoinHandle<()> {
     thread::spawn(move || {
-        let server = Server::new(config, conn_info,
-            db_name, vm_args, is_sequencer);
-        let result = match execute_server_thread(&server, barrier,
-                stop_sign, action) {
-            Err(e) => {
-                error!("Server {} (on {}) occurs an error: {}",
-                    server.id(), server.ip(), e);
-                ThreadResult::Failed
-            },
-            _ => ThreadResult::ServerSucceed
-        };
+        let result = execute_server_thread(&server, barrier,
+                &stop_sign, action, &timeouts)
+            .map(|_| ThreadResult::ServerSucceed);
         if server.is_sequencer() {
             debug!("The sequencer finished.");
         } else {
             debug!("Server {} finished.", server.id());
         }
-        result_ch.send(result).unwrap();
+        super::send_result(&result_ch, result,
+            &format!("Server {}", server.id()), server.ip(),
+            || server.dump_threads());
     })
 }
 
 fn execute_server_thread(server: &Server, barrier: Arc<Barrier>,
-    stop_sign: Arc<RwLock<bool>>, action: Action) -> Result<()> {
+    stop_sign: &RwLock<StopSign>, action: Action,
+    timeouts: &Timeouts) -> Result<()> {
 
     if server.id() == 0 {
         info!("Preparing servers...");
     }
 
-    server.send_bench_dir()?;
-
     match action {
         Action::Loading => {
             server.delete_db_dir()?;
@@ -60,18 +50,17 @@ fn execute_server_thread(server: &Server, barrier: Arc<Barrier>,
     // Wait for other servers prepared
     barrier.wait();
 
+    let name = if server.is_sequencer() {
+        "the sequencer".to_owned()
+    } else {
+        format!("server {}", server.id())
+    };
     if server.is_sequencer() {
         barrier.wait(); // wait for normal servers ready
-        server.start()?;
-        while !server.check_for_ready()? {
-            thread::sleep(Duration::from_secs(CHECKING_INTERVAL));
-        }
+        start_and_wait_for_ready(server, &name, stop_sign, timeouts)?;
         debug!("The sequencer is ready.");
     } else {
-        server.start()?;
-        while !server.check_for_ready()? {
-            thread::sleep(Duration::from_secs(CHECKING_INTERVAL));
-        }
+        start_and_wait_for_ready(server, &name, stop_sign, timeouts)?;
         debug!("Server {} is ready.", server.id());
         barrier.wait(); // wait for other normal servers ready
     }
@@ -82,20 +71,34 @@ fn execute_server_thread(server: &Server, barrier: Arc<Barrier>,
     if server.id() == 0 {
         info!("All servers are ready.");
     }
-    
+
     // Wait for client started
     barrier.wait();
 
-    let mut stop = false;
-    while !stop {
+    while *stop_sign.read()? == StopSign::Running {
         server.check_for_error()?;
         thread::sleep(Duration::from_secs(CHECKING_INTERVAL));
-        stop = *(stop_sign.read()?);
     }
+    // The stop sign is also set when the job is aborted
+    super::check_aborted(stop_sign)?;
 
     if let Action::Loading = action {
         server.backup_db()?;
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+fn start_and_wait_for_ready(server: &Server, name: &str,
+        stop_sign: &RwLock<StopSign>, timeouts: &Timeouts) -> Result<()> {
+    server.start()?;
+    // A process failing on start may have written the error to its log
+    let started = || {
+        server.check_for_error()?;
+        server.is_running()
+    };
+    super::wait_for(started, Some(timeouts.startup),
+        &format!("{} did not start", name), stop_sign)?;
+    super::wait_for(|| server.check_for_ready(), Some(timeouts.ready),
+        &format!("{} was not ready", name), stop_sign)
+}
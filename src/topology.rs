@@ -0,0 +1,215 @@
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::{Result, BenchError};
+use crate::config::{Machines, Ports};
+use crate::connections::{Action, ConnectionInfo, PortAllocator};
+
+/// The machine of a process, written as `ip` or `ip:port`. A process
+/// without a port gets a free one in the range of its role.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Placement {
+    pub ip: String,
+    pub port: Option<usize>
+}
+
+impl TryFrom<String> for Placement {
+    type Error = String;
+
+    fn try_from(address: String) -> std::result::Result<Placement, String> {
+        match address.rsplit_once(':') {
+            Some((ip, port)) => {
+                let port: u16 = port.parse()
+                    .map_err(|_| format!("invalid port in '{}'", address))?;
+                Ok(Placement {
+                    ip: ip.to_owned(),
+                    port: Some(port as usize)
+                })
+            },
+            None => Ok(Placement {
+                ip: address,
+                port: None
+            })
+        }
+    }
+}
+
+/// Pins the processes of a job to the machines instead of placing
+/// them round-robin. The i-th server (client) in the lists gets id i.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Topology {
+    // Replaces the sequencer in the config if it is set
+    pub sequencer: Option<Placement>,
+    pub servers: Vec<Placement>,
+    pub clients: Vec<Placement>
+}
+
+type Connections = (Option<ConnectionInfo>, Vec<ConnectionInfo>, Vec<ConnectionInfo>);
+
+impl Topology {
+    pub fn from_file(path: &Path) -> Result<Topology> {
+        let content = fs::read_to_string(path).map_err(|e| BenchError::Message(
+            format!("cannot read the topology file '{}': {}", path.display(), e)))?;
+        toml::from_str(&content).map_err(|e| BenchError::Message(
+            format!("invalid topology file '{}': {}", path.display(), e)))
+    }
+
+    /// Output: the connection info of the sequencer, the servers and the
+    /// clients. Loading only uses the first client.
+    pub fn place(&self, machines: &Machines, ports: &Ports, server_count: usize,
+            client_count: usize, action: Action) -> Result<Connections> {
+        if self.servers.len() != server_count {
+            return Err(BenchError::Message(format!(
+                "the topology has {} servers, but server_count is {}",
+                self.servers.len(), server_count)));
+        }
+        let clients = match action {
+            Action::Loading => &self.clients[.. self.clients.len().min(1)],
+            Action::Benchmarking => &self.clients[..]
+        };
+        let client_count = if let Action::Loading = action { 1 } else { client_count };
+        if clients.len() != client_count {
+            return Err(BenchError::Message(format!(
+                "the topology has {} clients, but {} are needed",
+                self.clients.len(), client_count)));
+        }
+
+        // The topology names the real machines, which are replaced
+        // by loopback addresses under `--local`
+        let resolve = |placement: &Placement| Placement {
+            ip: machines.address(&placement.ip).to_owned(),
+            port: placement.port
+        };
+        let servers: Vec<Placement> = self.servers.iter().map(resolve).collect();
+        let clients: Vec<Placement> = clients.iter().map(resolve).collect();
+
+        let sequencer = self.sequencer.as_ref().map(resolve).or_else(||
+            machines.sequencer.clone().map(|ip| Placement { ip, port: None }));
+        let placements = sequencer.iter().chain(&servers).chain(&clients);
+
+        // The given ports go first so that the others are taken around them
+        let mut allocator = PortAllocator::new();
+        for placement in placements {
+            if !machines.all.contains(&placement.ip) {
+                return Err(BenchError::Message(format!(
+                    "'{}' in the topology is not a machine in the config",
+                    placement.ip)));
+            }
+            if let Some(port) = placement.port {
+                allocator.reserve(&placement.ip, port)?;
+            }
+        }
+
+        let mut connect = |id, placement: &Placement, range| -> Result<ConnectionInfo> {
+            let port = match placement.port {
+                Some(port) => port,
+                None => allocator.allocate(&placement.ip, range)?
+            };
+            Ok(ConnectionInfo {
+                id,
+                ip: placement.ip.clone(),
+                port
+            })
+        };
+
+        let sequencer = match &sequencer {
+            Some(placement) => Some(connect(server_count, placement, &ports.sequencer)?),
+            None => None
+        };
+        let servers = servers.iter().enumerate()
+            .map(|(id, placement)| connect(id, placement, &ports.servers))
+            .collect::<Result<Vec<_>>>()?;
+        let clients = clients.iter().enumerate()
+            .map(|(id, placement)| connect(id, placement, &ports.clients))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok((sequencer, servers, clients))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn machines() -> Machines {
+        let ips = |list: &[&str]| list.iter().map(|ip| ip.to_string()).collect::<Vec<_>>();
+        Machines {
+            all: ips(&["10.0.0.1", "10.0.0.1", "10.0.0.2", "10.0.0.3"]),
+            sequencer: Some("10.0.0.1".to_owned()),
+            servers: ips(&["10.0.0.1", "10.0.0.2"]),
+            clients: ips(&["10.0.0.3"]),
+            hosts: Default::default(),
+            local_addresses: Default::default()
+        }
+    }
+
+    fn addresses(list: &[ConnectionInfo]) -> Vec<String> {
+        list.iter().map(|c| format!("{}:{}", c.ip, c.port)).collect()
+    }
+
+    #[test]
+    fn test_place() {
+        let topology: Topology = toml::from_str(r#"
+            servers = ["10.0.0.2", "10.0.0.1:30000", "10.0.0.2:31000"]
+            clients = ["10.0.0.1", "10.0.0.3", "10.0.0.3"]
+        "#).unwrap();
+        let (sequencer, servers, clients) = topology.place(&machines(),
+            &Ports::default(), 3, 3, Action::Benchmarking).unwrap();
+
+        // The sequencer of the config moves aside for the given port
+        let sequencer = sequencer.unwrap();
+        assert_eq!((sequencer.id, sequencer.port), (3, 30001));
+        assert_eq!(addresses(&servers), ["10.0.0.2:30000", "10.0.0.1:30000",
+            "10.0.0.2:31000"]);
+        assert_eq!(addresses(&clients), ["10.0.0.1:30002", "10.0.0.3:30000",
+            "10.0.0.3:30001"]);
+
+        let (_, _, clients) = topology.place(&machines(), &Ports::default(),
+            3, 6, Action::Loading).unwrap();
+        assert_eq!(addresses(&clients), ["10.0.0.1:30002"]);
+    }
+
+    #[test]
+    fn test_invalid_topologies() {
+        let place = |content: &str| toml::from_str::<Topology>(content).unwrap()
+            .place(&machines(), &Ports::default(), 1, 1, Action::Benchmarking);
+
+        assert!(place(r#"servers = ["10.0.0.2"]
+            clients = ["10.0.0.3"]"#).is_ok());
+        // Not a machine in the config
+        assert!(place(r#"servers = ["10.0.0.9"]
+            clients = ["10.0.0.3"]"#).is_err());
+        // The numbers of the processes do not match
+        assert!(place(r#"servers = ["10.0.0.2", "10.0.0.1"]
+            clients = ["10.0.0.3"]"#).is_err());
+        // The same port twice
+        assert!(place(r#"sequencer = "10.0.0.2:30005"
+            servers = ["10.0.0.2:30005"]
+            clients = ["10.0.0.3"]"#).is_err());
+        assert!(toml::from_str::<Topology>(r#"servers = ["10.0.0.2:port"]
+            clients = []"#).is_err());
+    }
+
+    #[test]
+    fn test_place_in_local_mode() {
+        let mut machines = machines();
+        machines.use_local_addresses();
+        let topology: Topology = toml::from_str(r#"
+            sequencer = "10.0.0.2"
+            servers = ["10.0.0.1", "10.0.0.2:31000"]
+            clients = ["10.0.0.3"]
+        "#).unwrap();
+        let (sequencer, servers, clients) = topology.place(&machines,
+            &Ports::default(), 2, 1, Action::Benchmarking).unwrap();
+
+        // 10.0.0.1 is known by the address of the sequencer
+        assert_eq!(addresses(&[sequencer.unwrap()]), ["127.0.1.2:30000"]);
+        assert_eq!(addresses(&servers), ["127.0.0.1:30000", "127.0.1.2:31000"]);
+        assert_eq!(addresses(&clients), ["127.0.2.1:30000"]);
+    }
+}
@@ -0,0 +1,112 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::{Result, BenchError};
+use crate::command::output_into_string;
+use super::{Transport, Stream};
+
+/// Runs everything on localhost. Each ip is treated as a machine
+/// and gets its own directory under the root directory, which works
+/// as the home directory of that machine. Relative remote paths are
+/// resolved in there.
+pub struct LocalTransport {
+    root_dir: PathBuf
+}
+
+impl LocalTransport {
+    pub fn new(root_dir: &Path) -> LocalTransport {
+        LocalTransport {
+            root_dir: root_dir.to_owned()
+        }
+    }
+
+    fn machine_dir(&self, ip: &str) -> Result<PathBuf> {
+        let dir = self.root_dir.join(ip);
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    fn local_dir(&self) -> Result<PathBuf> {
+        Ok(std::env::current_dir()?)
+    }
+}
+
+impl Transport for LocalTransport {
+    fn exec(&self, ip: &str, cmd: &str) -> Result<String> {
+        let mut command = Command::new("sh");
+        command.current_dir(self.machine_dir(ip)?)
+            .arg("-c").arg(cmd);
+
+        output_into_string(command).map_err(|e| e.into_remote_if_possible(ip))
+    }
+
+    fn stream(&self, ip: &str, cmd: &str) -> Result<Stream> {
+        let mut command = Command::new("sh");
+        command.current_dir(self.machine_dir(ip)?)
+            .arg("-c").arg(cmd);
+
+        Stream::spawn(command)
+    }
+
+    fn upload(&self, is_dir: bool, ip: &str, local_path: &str,
+            remote_path: &str) -> Result<()> {
+        let mut command = Command::new("cp");
+
+        if is_dir {
+            command.arg("-r");
+        }
+
+        command.arg(local_path);
+        command.arg(self.machine_dir(ip)?.join(remote_path));
+
+        match output_into_string(command) {
+            Err(BenchError::CommandFailed(_, 1, _)) =>
+                Err(BenchError::FileNotFound(local_path.to_owned())),
+            other => other.map(|_| ())
+        }
+    }
+
+    fn download(&self, is_dir: bool, ip: &str, remote_path: &str,
+            local_path: &str) -> Result<()> {
+        // Use a shell so that the remote path can be a pattern as scp
+        let local_path = self.local_dir()?.join(local_path);
+        let cmd = format!("cp {} {} {}",
+            if is_dir { "-r" } else { "" },
+            remote_path,
+            local_path.display()
+        );
+
+        match self.exec(ip, &cmd) {
+            Err(BenchError::CommandFailedOnRemote(_, _, 1, _)) =>
+                Err(BenchError::FileNotFound(remote_path.to_owned())),
+            other => other.map(|_| ())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_path;
+
+    #[test]
+    fn test_machine_dirs() {
+        let root = temp_path("local");
+        let transport = LocalTransport::new(&root);
+
+        transport.exec("127.0.1.1", "mkdir work && echo hi > work/a.txt")
+            .unwrap();
+        assert!(root.join("127.0.1.1/work/a.txt").is_file());
+        assert!(transport.exists("127.0.1.1", "work/a.txt").unwrap());
+        assert!(!transport.exists("127.0.1.2", "work/a.txt").unwrap());
+
+        let local_file = root.join("b.txt");
+        fs::write(&local_file, "b").unwrap();
+        transport.upload(false, "127.0.1.2", local_file.to_str().unwrap(),
+            "b.txt").unwrap();
+        assert_eq!(transport.exec("127.0.1.2", "cat b.txt").unwrap(), "b");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
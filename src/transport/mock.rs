@@ -0,0 +1,62 @@
+use std::io::{self, Cursor};
+use std::sync::Mutex;
+
+use crate::error::Result;
+use super::{Transport, Stream};
+
+type Responder = dyn Fn(&str, &str) -> Result<String> + Send + Sync;
+
+/// A transport for testing. It records every request and answers
+/// the executed commands using the given responder.
+pub struct MockTransport {
+    responder: Box<Responder>,
+    records: Mutex<Vec<String>>
+}
+
+impl MockTransport {
+    pub fn new<F>(responder: F) -> MockTransport
+            where F: Fn(&str, &str) -> Result<String> + Send + Sync + 'static {
+        MockTransport {
+            responder: Box::new(responder),
+            records: Mutex::new(Vec::new())
+        }
+    }
+
+    pub fn records(&self) -> Vec<String> {
+        self.records.lock().unwrap().clone()
+    }
+
+    fn record(&self, record: String) {
+        self.records.lock().unwrap().push(record);
+    }
+}
+
+impl Transport for MockTransport {
+    fn exec(&self, ip: &str, cmd: &str) -> Result<String> {
+        self.record(format!("exec {} {}", ip, cmd));
+        (self.responder)(ip, cmd)
+    }
+
+    // The stream outputs the response at once and ends
+    fn stream(&self, ip: &str, cmd: &str) -> Result<Stream> {
+        self.record(format!("stream {} {}", ip, cmd));
+        let output = (self.responder)(ip, cmd)?;
+        Ok(Stream {
+            output: Box::new(Cursor::new(output.into_bytes())),
+            input: Box::new(io::sink()),
+            process: None
+        })
+    }
+
+    fn upload(&self, _: bool, ip: &str, local_path: &str,
+            remote_path: &str) -> Result<()> {
+        self.record(format!("upload {} {} {}", ip, local_path, remote_path));
+        Ok(())
+    }
+
+    fn download(&self, _: bool, ip: &str, remote_path: &str,
+            local_path: &str) -> Result<()> {
+        self.record(format!("download {} {} {}", ip, remote_path, local_path));
+        Ok(())
+    }
+}
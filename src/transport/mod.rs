@@ -0,0 +1,85 @@
+mod openssh;
+mod local;
+#[cfg(test)]
+pub mod mock;
+
+pub use openssh::OpenSshTransport;
+pub use local::LocalTransport;
+
+use std::io::{Read, Write};
+use std::process::{Child, Stdio};
+use std::sync::Arc;
+
+use crate::error::{Result, BenchError};
+use crate::config::Config;
+
+/// The way the auto-bencher reaches a machine in the cluster.
+///
+/// Every remote action (executing commands, copying files) goes
+/// through this trait, so the orchestration logic does not need to
+/// know how the machines are actually connected.
+pub trait Transport: Send + Sync {
+    /// Executes the command on the machine.
+    ///
+    /// Returns: shown messages
+    fn exec(&self, ip: &str, cmd: &str) -> Result<String>;
+
+    /// Copies a local file (or a directory) to the machine.
+    fn upload(&self, is_dir: bool, ip: &str, local_path: &str,
+        remote_path: &str) -> Result<()>;
+
+    /// Copies a file (or a directory) on the machine to local.
+    fn download(&self, is_dir: bool, ip: &str, remote_path: &str,
+        local_path: &str) -> Result<()>;
+
+    /// Runs the command on the machine without waiting for it, so its
+    /// output can be read while it is running.
+    fn stream(&self, ip: &str, cmd: &str) -> Result<Stream>;
+
+    /// Releases the connections kept for the machines. It is called
+    /// once the auto-bencher finishes.
+    fn close(&self) {}
+
+    /// Checks if the given path exists on the machine.
+    fn exists(&self, ip: &str, remote_path: &str) -> Result<bool> {
+        let cmd = format!("test -e {}", remote_path);
+        match self.exec(ip, &cmd) {
+            Ok(_) => Ok(true),
+            Err(BenchError::CommandFailedOnRemote(_, _, 1, _)) => Ok(false),
+            Err(e) => Err(e)
+        }
+    }
+}
+
+/// The output of a command that keeps running on the machine.
+/// Closing the input tells the command to stop.
+pub struct Stream {
+    pub output: Box<dyn Read + Send>,
+    pub input: Box<dyn Write + Send>,
+    // The local process relaying the command, which should be waited
+    // after the output ends
+    pub process: Option<Child>
+}
+
+impl Stream {
+    fn spawn(mut command: std::process::Command) -> Result<Stream> {
+        let mut process = command.stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        Ok(Stream {
+            output: Box::new(process.stdout.take().unwrap()),
+            input: Box::new(process.stdin.take().unwrap()),
+            process: Some(process)
+        })
+    }
+}
+
+pub fn create(config: &Config) -> Arc<dyn Transport> {
+    if config.local.enabled {
+        Arc::new(LocalTransport::new(&config.local_root_dir()))
+    } else {
+        Arc::new(OpenSshTransport::new(config))
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::*;
+
+use crate::error::{Result, BenchError};
+use crate::command::output_into_string;
+use crate::config::{Config, HostConfig};
+use super::{Transport, Stream};
+
+// The master connections exit by themselves after being idle for this
+// long, in case the auto-bencher is killed before closing them.
+const CONTROL_PERSIST_SECS: u64 = 60;
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Reaches the machines by calling the `ssh` and `scp` programs
+/// installed on the local system. The machines declared with their
+/// own settings (user, port, identity file, jump host) use them.
+///
+/// Each machine gets one master connection (ssh's ControlMaster),
+/// which is shared by all the commands sent to it, so only the first
+/// command pays for the handshake. A machine falls back to separate
+/// connections for a while if its master connection cannot be set up.
+pub struct OpenSshTransport {
+    user_name: String,
+    hosts: HashMap<String, HostConfig>,
+    control_dir: PathBuf,
+    connections: Mutex<HashMap<String, Arc<Mutex<HostConnection>>>>
+}
+
+#[derive(Default)]
+struct HostConnection {
+    // The last time the master connection was known to be alive
+    checked_at: Option<Instant>,
+    // The last time a master connection could not be set up
+    failed_at: Option<Instant>,
+    // The number of master connections set up (the handshakes made)
+    masters: usize,
+    // The number of master connections that could not be set up
+    failed_masters: usize,
+    // The number of ssh and scp calls
+    commands: usize,
+    // The calls that could not use the master connection
+    direct: usize
+}
+
+impl OpenSshTransport {
+    pub fn new(config: &Config) -> OpenSshTransport {
+        OpenSshTransport {
+            user_name: config.system.user_name.clone(),
+            hosts: config.machines.hosts.clone(),
+            control_dir: std::env::temp_dir().join(
+                format!("auto-bencher-ssh-{}", std::process::id())),
+            connections: Mutex::new(HashMap::new())
+        }
+    }
+
+    fn connection(&self, ip: &str) -> Arc<Mutex<HostConnection>> {
+        self.connections.lock().unwrap().entry(ip.to_owned())
+            .or_default().clone()
+    }
+
+    fn destination(&self, ip: &str) -> String {
+        let user = self.hosts.get(ip)
+            .and_then(|host| host.user.as_ref())
+            .unwrap_or(&self.user_name);
+        format!("{}@{}", user, ip)
+    }
+
+    // The options understood by both ssh and scp for reaching the machine
+    fn host_options(&self, ip: &str) -> Vec<String> {
+        let host = match self.hosts.get(ip) {
+            Some(host) => host,
+            None => return Vec::new()
+        };
+
+        let mut options = Vec::new();
+        if let Some(port) = host.ssh_port {
+            options.push(format!("Port={}", port));
+        }
+        if let Some(identity_file) = &host.identity_file {
+            options.push(format!("IdentityFile={}", identity_file));
+        }
+        if let Some(jump_host) = &host.jump_host {
+            options.push(format!("ProxyJump={}", jump_host));
+        }
+        options.into_iter().flat_map(|option| vec!["-o".to_owned(), option])
+            .collect()
+    }
+
+    fn control_path(&self, ip: &str) -> PathBuf {
+        self.control_dir.join(ip)
+    }
+
+    // Output: the options for ssh and scp to reach the machine, which
+    // go through the master connection if there is one
+    fn multiplex(&self, ip: &str) -> Vec<String> {
+        let connection = self.connection(ip);
+        let mut connection = connection.lock().unwrap();
+        connection.commands += 1;
+
+        let mut options = self.host_options(ip);
+        let is_fresh = connection.checked_at
+            .is_some_and(|t| t.elapsed() < HEALTH_CHECK_INTERVAL);
+        // Do not pay for another failing handshake on every command
+        let failed_recently = connection.failed_at
+            .is_some_and(|t| t.elapsed() < HEALTH_CHECK_INTERVAL);
+        if is_fresh || (!failed_recently && self.connect(ip, &mut connection)) {
+            options.extend(vec![
+                "-o".to_owned(),
+                format!("ControlPath={}", self.control_path(ip).display()),
+                "-o".to_owned(),
+                "ControlMaster=no".to_owned()
+            ]);
+        } else {
+            connection.direct += 1;
+        }
+        options
+    }
+
+    // Makes sure the master connection is alive, setting up a new
+    // one if it is not.
+    fn connect(&self, ip: &str, connection: &mut HostConnection) -> bool {
+        connection.checked_at = None;
+        if self.check_master(ip) {
+            connection.checked_at = Some(Instant::now());
+            return true;
+        }
+
+        // A stale socket stops ssh from creating the new one
+        let control_path = self.control_path(ip);
+        let _ = fs::remove_file(&control_path);
+        if fs::create_dir_all(&self.control_dir).is_err() {
+            return false;
+        }
+
+        debug!("Opening a shared connection to '{}'...", ip);
+        // -f makes ssh go to the background after the authentication.
+        // The master has its own process group, so Ctrl-C does not
+        // take it down before the processes are torn down.
+        let status = Command::new("ssh")
+            .arg("-M").arg("-N").arg("-f")
+            .arg("-S").arg(&control_path)
+            .arg("-o").arg(format!("ControlPersist={}", CONTROL_PERSIST_SECS))
+            .args(self.host_options(ip))
+            .arg(self.destination(ip))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .process_group(0)
+            .status();
+        match status {
+            Ok(status) if status.success() => {
+                connection.masters += 1;
+                connection.checked_at = Some(Instant::now());
+                connection.failed_at = None;
+                true
+            },
+            _ => {
+                debug!("Cannot open a shared connection to '{}'. \
+                    Using separate connections.", ip);
+                connection.failed_masters += 1;
+                connection.failed_at = Some(Instant::now());
+                false
+            }
+        }
+    }
+
+    fn check_master(&self, ip: &str) -> bool {
+        let mut command = Command::new("ssh");
+        command.arg("-S").arg(self.control_path(ip))
+            .arg("-O").arg("check")
+            .arg(self.destination(ip));
+        output_into_string(command).is_ok()
+    }
+
+    // Checks if a failure is caused by a broken master connection.
+    // If it is, the next command will set up a new one.
+    fn is_master_lost(&self, ip: &str, options: &[String]) -> bool {
+        let is_multiplexed = options.iter().any(|o| o.starts_with("ControlPath="));
+        if !is_multiplexed || self.check_master(ip) {
+            return false;
+        }
+        debug!("The shared connection to '{}' is lost. Reconnecting...", ip);
+        self.connection(ip).lock().unwrap().checked_at = None;
+        true
+    }
+
+    // Runs the command built with the multiplexing options, and retries
+    // once on a new master connection if the old one is broken.
+    fn run<F, P>(&self, ip: &str, build: F, should_retry: P) -> Result<String>
+            where F: Fn(&[String]) -> Command, P: Fn(&BenchError) -> bool {
+        let options = self.multiplex(ip);
+        let result = output_into_string(build(&options))
+            .map_err(|e| e.into_remote_if_possible(ip));
+        match result {
+            Err(ref e) if should_retry(e) && self.is_master_lost(ip, &options) => {
+                let options = self.multiplex(ip);
+                output_into_string(build(&options))
+                    .map_err(|e| e.into_remote_if_possible(ip))
+            },
+            other => other
+        }
+    }
+
+    fn scp(&self, ip: &str, is_dir: bool, from: &str, to: &str) -> Result<String> {
+        self.run(ip, |options| {
+            let mut command = Command::new("scp");
+            command.args(options);
+            if is_dir {
+                command.arg("-r");
+            }
+            command.arg(from).arg(to);
+            command
+        }, |_| true)
+    }
+}
+
+impl Transport for OpenSshTransport {
+    fn exec(&self, ip: &str, cmd: &str) -> Result<String> {
+        // ssh exits with 255 if the connection fails
+        self.run(ip, |options| {
+            let mut command = Command::new("ssh");
+            command.args(options).arg(self.destination(ip)).arg(cmd);
+            command
+        }, |e| matches!(e, BenchError::CommandFailedOnRemote(_, _, 255, _)))
+    }
+
+    fn stream(&self, ip: &str, cmd: &str) -> Result<Stream> {
+        let mut command = Command::new("ssh");
+        command.args(self.multiplex(ip)).arg(self.destination(ip)).arg(cmd);
+
+        Stream::spawn(command)
+    }
+
+    fn upload(&self, is_dir: bool, ip: &str, local_path: &str,
+            remote_path: &str) -> Result<()> {
+        let to = format!("{}:{}", self.destination(ip), remote_path);
+        match self.scp(ip, is_dir, local_path, &to) {
+            Err(BenchError::CommandFailedOnRemote(_, _, 2, _)) =>
+                Err(BenchError::FileNotFound(local_path.to_owned())),
+            other => other.map(|_| ())
+        }
+    }
+
+    fn download(&self, is_dir: bool, ip: &str, remote_path: &str,
+            local_path: &str) -> Result<()> {
+        let from = format!("{}:{}", self.destination(ip), remote_path);
+        match self.scp(ip, is_dir, &from, local_path) {
+            Err(BenchError::CommandFailedOnRemote(_, _, 2, _)) =>
+                Err(BenchError::FileNotFound(remote_path.to_owned())),
+            other => other.map(|_| ())
+        }
+    }
+
+    fn close(&self) {
+        let connections = self.connections.lock().unwrap();
+        let mut ips: Vec<&String> = connections.keys().collect();
+        ips.sort();
+
+        for ip in ips {
+            let connection = connections[ip].lock().unwrap();
+            let handshakes = connection.masters + connection.failed_masters
+                + connection.direct;
+            debug!("'{}': {} ssh/scp calls over {} handshakes ({} saved)",
+                ip, connection.commands, handshakes,
+                connection.commands.saturating_sub(handshakes));
+
+            if connection.masters > 0 {
+                let mut command = Command::new("ssh");
+                command.arg("-S").arg(self.control_path(ip))
+                    .arg("-O").arg("exit")
+                    .arg(self.destination(ip));
+                if let Err(e) = output_into_string(command) {
+                    debug!("Failed to close the shared connection to '{}': {}",
+                        ip, e);
+                }
+            }
+        }
+        let _ = fs::remove_dir_all(&self.control_dir);
+    }
+}